@@ -0,0 +1,394 @@
+// --- 方法二（Linux）：AT-SPI2 ---
+// Windows 上的 UI Automation 在 Linux 上对应的是 AT-SPI2：程序通过一条独立的 D-Bus 总线
+// （无障碍总线）公开界面元素，屏幕阅读器就是靠它工作的。
+// 这里订阅焦点变化和文本选择变化事件，从焦点元素的 Text 接口读取选中的文本，
+// 行为和 `ui_automation_improved` 保持一致：多选区、屏幕位置、所在句子和段落、不读取密码框。
+//
+// 无障碍总线的地址从会话总线上的 org.a11y.Bus 获取，也可以用环境变量 AT_SPI_BUS_ADDRESS 指定。
+// 在没有桌面的环境里可以这样测试：
+//
+//     dbus-run-session -- sh -c '
+//         Xvfb :99 & export DISPLAY=:99
+//         /usr/libexec/at-spi-bus-launcher --launch-immediately &
+//         gtk3-widget-factory & text_listener uia --format jsonl'
+//
+// 然后用 xdotool 在 GTK 程序里选中文本，标准输出上就会出现捕获。
+// 不需要 X 服务器的自动测试见 tests/atspi.rs，它在无障碍总线上模拟一个程序。
+
+use crate::{
+    capture::{self, process_image, CaptureEvent, CaptureKind, CaptureMode, CaptureSource, ScreenRect, SelectedRange, StopToken, TextContext, WindowInfo},
+    rules::{self, RuleSet},
+};
+use futures_lite::{FutureExt, StreamExt};
+use std::{env, sync::mpsc::Sender, time::Duration};
+use zbus::{
+    blocking::{connection, Connection},
+    message::Type as MessageType,
+    zvariant::{DynamicType, OwnedObjectPath, OwnedValue},
+    MatchRule, Message, MessageStream,
+};
+
+// 需要向注册中心登记的事件，没有人登记时程序不会发出这些事件
+const EVENTS: &[&str] = &["object:text-selection-changed", "object:state-changed:focused"];
+
+const EVENT_INTERFACE: &str = "org.a11y.atspi.Event.Object";
+const ACCESSIBLE: &str = "org.a11y.atspi.Accessible";
+const TEXT: &str = "org.a11y.atspi.Text";
+
+// AtspiRole 中用到的几个值
+const ROLE_DIALOG: u32 = 16;
+const ROLE_FRAME: u32 = 23;
+const ROLE_PASSWORD_TEXT: u32 = 40;
+const ROLE_WINDOW: u32 = 69;
+
+// AtspiTextGranularity
+const GRANULARITY_SENTENCE: u32 = 2;
+const GRANULARITY_PARAGRAPH: u32 = 4;
+
+// 向上查找所在窗口时最多经过的层数
+const MAX_PARENT_DEPTH: usize = 32;
+
+// 每次等待事件的最长时间，保证停止信号能在这个时间内被发现
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// AT-SPI 捕获源：订阅焦点变化和文本选择变化事件，读取选中的文本
+#[derive(Debug, Clone, Default)]
+pub struct AtspiSource {
+    /// 被拒绝的窗口不会读取其中的文本
+    pub rules: RuleSet,
+    /// 没有选中文本时输出输入框的全部内容（`CaptureKind::FieldValue`）。密码框始终不读取
+    pub field_values: bool,
+}
+
+impl CaptureSource for AtspiSource {
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::UiAutomation
+    }
+
+    fn run(&mut self, events: &Sender<CaptureEvent>, stop: &StopToken) {
+        let conn = match connect() {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("[错误] 无法连接无障碍总线: {}", e);
+                return;
+            }
+        };
+
+        // 先建立消息流再登记事件，避免漏掉登记之后马上发出的事件
+        let rule = match MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface(EVENT_INTERFACE)
+            .map(|builder| builder.build())
+        {
+            Ok(rule) => rule,
+            Err(e) => {
+                eprintln!("[错误] {}", e);
+                return;
+            }
+        };
+        let mut stream = match async_io::block_on(MessageStream::for_match_rule(rule, conn.inner(), None)) {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("[错误] 订阅无障碍事件失败: {}", e);
+                return;
+            }
+        };
+        for event in EVENTS {
+            if let Err(e) = call::<_, ()>(&conn, "org.a11y.atspi.Registry", "/org/a11y/atspi/registry", "org.a11y.atspi.Registry", "RegisterEvent", &(*event,)) {
+                eprintln!("[错误] 登记事件 {} 失败: {}", event, e);
+                return;
+            }
+        }
+        eprintln!("[状态] AT-SPI 已初始化，开始监听事件...");
+
+        let mut reader = Reader {
+            conn: &conn,
+            source: self,
+            window: None,
+            last_text: String::new(),
+        };
+        while !stop.is_stopped() {
+            let next = async_io::block_on(stream.next().or(async {
+                async_io::Timer::after(STOP_CHECK_INTERVAL).await;
+                None
+            }));
+            let message = match next {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => {
+                    eprintln!("[警告] 读取无障碍事件失败: {}", e);
+                    continue;
+                }
+                None => continue,
+            };
+            if stop.is_paused() {
+                continue;
+            }
+            if let Some(event) = reader.handle(&message) {
+                if events.send(event).is_err() {
+                    break;
+                }
+            }
+        }
+
+        for event in EVENTS {
+            let _ = call::<_, ()>(&conn, "org.a11y.atspi.Registry", "/org/a11y/atspi/registry", "org.a11y.atspi.Registry", "DeregisterEvent", &(*event,));
+        }
+        eprintln!("[状态] AT-SPI 监听已停止。");
+    }
+}
+
+pub fn run() {
+    println!("方法二：AT-SPI 模式已启动。");
+    println!("这个版本会订阅焦点变化和文本选择事件，选中文本后立即输出。");
+    println!("需要程序开启无障碍支持（GTK、Qt、Firefox、Chromium 等在无障碍总线运行时会自动开启）。");
+    println!("退出方式：按回车键停止并返回菜单");
+    println!("\n[提示] 请在不同的应用中选择文本，程序会自动检测...");
+
+    capture::run_until_enter(AtspiSource {
+        rules: rules::load_default_or_builtin(),
+        field_values: false,
+    });
+}
+
+// 连接无障碍总线：优先使用 AT_SPI_BUS_ADDRESS，否则向会话总线上的 org.a11y.Bus 询问地址
+fn connect() -> zbus::Result<Connection> {
+    let address = match env::var("AT_SPI_BUS_ADDRESS") {
+        Ok(address) if !address.is_empty() => address,
+        _ => {
+            let session = Connection::session()?;
+            call(&session, "org.a11y.Bus", "/org/a11y/bus", "org.a11y.Bus", "GetAddress", &())?
+        }
+    };
+    connection::Builder::address(address.as_str())?.build()
+}
+
+// 调用一个方法并解析返回值
+fn call<B, R>(conn: &Connection, destination: &str, path: &str, interface: &str, method: &str, body: &B) -> zbus::Result<R>
+where
+    B: serde::Serialize + DynamicType,
+    R: for<'d> zbus::zvariant::DynamicDeserialize<'d>,
+{
+    conn.call_method(Some(destination), path, Some(interface), method, body)?
+        .body()
+        .deserialize()
+}
+
+// 处理事件时需要的状态
+struct Reader<'a> {
+    conn: &'a Connection,
+    source: &'a AtspiSource,
+    window: Option<WindowInfo>,
+    last_text: String,
+}
+
+impl Reader<'_> {
+    fn handle(&mut self, message: &Message) -> Option<CaptureEvent> {
+        let header = message.header();
+        let sender = header.sender()?.to_string();
+        let path = header.path()?.to_string();
+        match header.member()?.as_str() {
+            "StateChanged" => {
+                // 参数的前三项是 (kind, detail1, detail2)，后面的内容在不同版本中不一样
+                let (kind, gained, _) = message.body().deserialize_unchecked::<(String, i32, i32)>().ok()?;
+                if kind != "focused" || gained != 1 {
+                    return None;
+                }
+                self.update_window(&sender, &path);
+                // 焦点移到一个已经有选区的控件上时也输出一次
+                self.read_selection(&sender, &path)
+            }
+            "TextSelectionChanged" => {
+                if self.window.is_none() {
+                    self.update_window(&sender, &path);
+                }
+                self.read_selection(&sender, &path)
+            }
+            _ => None,
+        }
+    }
+
+    // 焦点元素所在的窗口变化时更新窗口信息
+    fn update_window(&mut self, sender: &str, path: &str) {
+        let info = self.window_of(sender, path);
+        if self.window.as_ref().is_some_and(|w| w.title == info.title && w.pid == info.pid) {
+            return;
+        }
+        eprintln!("[事件] 窗口切换到: {}", info.title);
+        if !self.source.rules.allows(Some(&info)) {
+            eprintln!("[规则] 不读取该窗口中的文本。");
+        }
+        self.window = Some(info);
+    }
+
+    // 沿着 Parent 向上找到所在的顶层窗口，读取标题；进程信息来自发送事件的总线连接
+    fn window_of(&self, sender: &str, path: &str) -> WindowInfo {
+        let mut current = (sender.to_string(), path.to_string());
+        let mut title = None;
+        for _ in 0..MAX_PARENT_DEPTH {
+            let Ok(role) = call::<_, u32>(self.conn, &current.0, &current.1, ACCESSIBLE, "GetRole", &()) else {
+                break;
+            };
+            if matches!(role, ROLE_FRAME | ROLE_WINDOW | ROLE_DIALOG) {
+                title = self.property::<String>(&current.0, &current.1, "Name");
+                break;
+            }
+            match self.property::<(String, OwnedObjectPath)>(&current.0, &current.1, "Parent") {
+                Some((bus, parent)) if parent.as_str() != "/org/a11y/atspi/null" => {
+                    current = (bus, parent.to_string());
+                }
+                _ => break,
+            }
+        }
+
+        let pid = call::<_, u32>(
+            self.conn,
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "GetConnectionUnixProcessID",
+            &(sender,),
+        )
+        .ok();
+        let (process, exe_path) = pid.map_or((None, None), process_image);
+        WindowInfo {
+            title: title.filter(|t| !t.is_empty()).unwrap_or_else(|| "未知窗口".to_string()),
+            process,
+            class: None,
+            pid,
+            exe_path,
+            url: None,
+        }
+    }
+
+    fn property<T>(&self, destination: &str, path: &str, name: &str) -> Option<T>
+    where
+        T: TryFrom<OwnedValue>,
+    {
+        let value: OwnedValue = call(self.conn, destination, path, "org.freedesktop.DBus.Properties", "Get", &(ACCESSIBLE, name)).ok()?;
+        T::try_from(value).ok()
+    }
+
+    fn read_selection(&mut self, sender: &str, path: &str) -> Option<CaptureEvent> {
+        if !self.source.rules.allows(self.window.as_ref()) {
+            return None;
+        }
+        // 密码框的内容绝不读取；无法确定时也按密码框处理
+        let role = call::<_, u32>(self.conn, sender, path, ACCESSIBLE, "GetRole", &()).ok()?;
+        if role == ROLE_PASSWORD_TEXT {
+            return None;
+        }
+
+        let text = TextElement {
+            conn: self.conn,
+            sender,
+            path,
+        };
+        let ranges = text.selected_ranges();
+        let event = if !ranges.is_empty() {
+            CaptureEvent::from_ranges(CaptureMode::UiAutomation, ranges, self.window.clone())
+        } else if self.source.field_values {
+            let value = text.all_text().filter(|value| !value.trim().is_empty())?;
+            CaptureEvent {
+                kind: CaptureKind::FieldValue,
+                ..CaptureEvent::new(CaptureMode::UiAutomation, value, self.window.clone())
+            }
+        } else {
+            return None;
+        };
+
+        // 避免重复显示相同内容
+        if event.text == self.last_text || event.text.len() <= 2 {
+            return None;
+        }
+        self.last_text = event.text.clone();
+        Some(event)
+    }
+}
+
+// 实现了 org.a11y.atspi.Text 的元素。偏移量都按字符计算
+struct TextElement<'a> {
+    conn: &'a Connection,
+    sender: &'a str,
+    path: &'a str,
+}
+
+impl TextElement<'_> {
+    fn call<B, R>(&self, method: &str, body: &B) -> zbus::Result<R>
+    where
+        B: serde::Serialize + DynamicType,
+        R: for<'d> zbus::zvariant::DynamicDeserialize<'d>,
+    {
+        call(self.conn, self.sender, self.path, TEXT, method, body)
+    }
+
+    fn selected_ranges(&self) -> Vec<SelectedRange> {
+        let count: i32 = self.call("GetNSelections", &()).unwrap_or(0);
+        let mut ranges = Vec::new();
+        for i in 0..count {
+            let Ok((anchor, focus)) = self.call::<_, (i32, i32)>("GetSelection", &(i,)) else {
+                continue;
+            };
+            // 从后往前选中时有的程序会把起点和终点反过来给出
+            let (start, end) = (anchor.min(focus), anchor.max(focus));
+            let Ok(text) = self.call::<_, String>("GetText", &(start, end)) else {
+                continue;
+            };
+            if text.trim().is_empty() {
+                continue;
+            }
+            let paragraph = self.context(start, end, GRANULARITY_PARAGRAPH);
+            // 有的程序不支持按句子取文本，或者给出的范围不包含整个选区，这时从段落里切
+            let sentence = self
+                .context(start, end, GRANULARITY_SENTENCE)
+                .filter(|sentence| sentence.end - sentence.start == (end - start) as usize)
+                .or_else(|| paragraph.as_ref().map(TextContext::sentence));
+            ranges.push(SelectedRange {
+                text,
+                rects: self.extents(start, end).into_iter().collect(),
+                sentence,
+                paragraph,
+            });
+        }
+        ranges
+    }
+
+    // 选区在屏幕上的外接矩形。AT-SPI 只提供整体范围，不像 UIA 那样按行给出
+    fn extents(&self, start: i32, end: i32) -> Option<ScreenRect> {
+        // 坐标类型 0 表示屏幕坐标
+        let (x, y, width, height): (i32, i32, i32, i32) = self.call("GetRangeExtents", &(start, end, 0u32)).ok()?;
+        (width > 0 && height > 0).then_some(ScreenRect {
+            left: x as f64,
+            top: y as f64,
+            width: width as f64,
+            height: height as f64,
+        })
+    }
+
+    // 选区所在的句子或段落，以及选区在其中的位置。
+    // GetStringAtOffset 只返回一个偏移所在的单位，选区跨越多个单位时，
+    // 和 Windows 上一样把终点扩展到最后一个选中字符所在单位的末尾
+    fn context(&self, start: i32, end: i32, granularity: u32) -> Option<TextContext> {
+        let (first, unit_start, first_end): (String, i32, i32) =
+            self.call("GetStringAtOffset", &(start, granularity)).ok()?;
+        if first.is_empty() || start < unit_start {
+            return None;
+        }
+        let (text, unit_end) = if end > first_end {
+            let (_, _, last_end): (String, i32, i32) = self.call("GetStringAtOffset", &(end - 1, granularity)).ok()?;
+            let unit_end = last_end.max(end);
+            (self.call::<_, String>("GetText", &(unit_start, unit_end)).ok()?, unit_end)
+        } else {
+            (first, first_end)
+        };
+        let length = text.chars().count();
+        Some(TextContext {
+            start: ((start - unit_start) as usize).min(length),
+            end: ((end.min(unit_end) - unit_start) as usize).min(length),
+            text,
+        })
+    }
+
+    fn all_text(&self) -> Option<String> {
+        self.call("GetText", &(0i32, -1i32)).ok()
+    }
+}
@@ -0,0 +1,941 @@
+// --- 公共抽象：捕获源 ---
+// 三种模式各自监听的方式不同，但产出的都是"某段文本在某个时刻从某个窗口被捕获"。
+// 统一成 CaptureSource + CaptureEvent 之后，调用方可以用同一种方式消费任何模式的捕获结果。
+
+use crate::{
+    clipboard_content::MIME_TEXT,
+    history::HistoryStore,
+    redact::{self, Redacted, Redactor},
+    server::ApiServer,
+};
+use chrono::{DateTime, Local, SecondsFormat};
+use std::{
+    fmt, io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// 捕获来自哪种模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureMode {
+    ClipboardPoll,
+    UiAutomation,
+    GlobalHook,
+    PrimarySelection,
+}
+
+impl CaptureMode {
+    // 控制台输出时使用的标题
+    pub fn banner(self) -> &'static str {
+        match self {
+            CaptureMode::ClipboardPoll => "剪贴板更新",
+            CaptureMode::UiAutomation => "UIA 捕获内容",
+            CaptureMode::GlobalHook => "自动捕获内容",
+            CaptureMode::PrimarySelection => "选中文本",
+        }
+    }
+
+    /// 稳定的英文标识，用于存储和命令行参数
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureMode::ClipboardPoll => "clipboard",
+            CaptureMode::UiAutomation => "uia",
+            CaptureMode::GlobalHook => "hook",
+            CaptureMode::PrimarySelection => "primary",
+        }
+    }
+}
+
+impl fmt::Display for CaptureMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for CaptureMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CaptureMode, String> {
+        match s {
+            "clipboard" => Ok(CaptureMode::ClipboardPoll),
+            "uia" => Ok(CaptureMode::UiAutomation),
+            "hook" => Ok(CaptureMode::GlobalHook),
+            "primary" => Ok(CaptureMode::PrimarySelection),
+            _ => Err(format!("未知的捕获模式: {}", s)),
+        }
+    }
+}
+
+/// 捕获发生时的来源窗口
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub title: String,
+    /// 窗口所属进程的可执行文件名，比如 `notepad.exe`
+    pub process: Option<String>,
+    /// 窗口类名，比如控制台窗口的 `ConsoleWindowClass`
+    pub class: Option<String>,
+    /// 窗口所属进程的 PID
+    pub pid: Option<u32>,
+    /// 可执行文件的完整路径；提升了权限的进程可能读不到
+    pub exe_path: Option<PathBuf>,
+    /// 浏览器当前标签页的网址，从地址栏读取（只在 Windows 上支持）
+    pub url: Option<String>,
+}
+
+// 会读取地址栏网址的浏览器
+#[cfg(windows)]
+const BROWSERS: &[&str] = &[
+    "chrome.exe",
+    "msedge.exe",
+    "firefox.exe",
+    "brave.exe",
+    "opera.exe",
+    "vivaldi.exe",
+];
+
+#[cfg(windows)]
+impl WindowInfo {
+    /// 读取指定窗口的标题、类名和所属进程。网址读取起来慢，要另外调用 `with_browser_url`
+    ///
+    /// # Safety
+    /// `hwnd` 必须是有效的窗口句柄（窗口可以已经销毁，但不能是随意构造的值）。
+    pub unsafe fn from_hwnd(hwnd: windows::Win32::Foundation::HWND) -> WindowInfo {
+        use windows::Win32::UI::WindowsAndMessaging::{GetClassNameW, GetWindowTextW};
+
+        let mut buffer = [0u16; 256];
+        let len = GetWindowTextW(hwnd, &mut buffer);
+        let title = if len > 0 {
+            String::from_utf16_lossy(&buffer[..len as usize])
+        } else {
+            "未知窗口".to_string()
+        };
+        let len = GetClassNameW(hwnd, &mut buffer);
+        let class = (len > 0).then(|| String::from_utf16_lossy(&buffer[..len as usize]));
+        let (pid, exe_path) = process_image(hwnd);
+        let process = exe_path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned());
+        WindowInfo {
+            title,
+            process,
+            class,
+            pid,
+            exe_path,
+            url: None,
+        }
+    }
+
+    /// 浏览器窗口补上当前标签页的网址，其他窗口原样返回。
+    ///
+    /// 同一个窗口和标题的网址会被缓存，第一次读取最多等待几百毫秒，所以应当在复制完成之后再调用。
+    ///
+    /// # Safety
+    /// 与 `from_hwnd` 相同，`hwnd` 必须是有效的窗口句柄。
+    pub unsafe fn with_browser_url(mut self, hwnd: windows::Win32::Foundation::HWND) -> WindowInfo {
+        let is_browser = self
+            .process
+            .as_deref()
+            .is_some_and(|process| BROWSERS.iter().any(|browser| process.eq_ignore_ascii_case(browser)));
+        if is_browser {
+            self.url = crate::ui_automation_improved::browser_url(hwnd, &self.title);
+        }
+        self
+    }
+
+    /// 复制完成之后给前台窗口补上网址。前台窗口已经换了（进程或标题不同）时不读取，免得张冠李戴
+    pub fn with_foreground_url(self) -> WindowInfo {
+        use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0 == 0 {
+                return self;
+            }
+            let current = WindowInfo::from_hwnd(hwnd);
+            if current.pid == self.pid && current.title == self.title {
+                self.with_browser_url(hwnd)
+            } else {
+                self
+            }
+        }
+    }
+
+    // 读取当前前台窗口，没有前台窗口时返回 None
+    pub fn foreground() -> Option<WindowInfo> {
+        use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0 == 0 {
+                None
+            } else {
+                Some(WindowInfo::from_hwnd(hwnd))
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl WindowInfo {
+    /// 读取 X11 的活动窗口（`_NET_ACTIVE_WINDOW`）；Wayland 下只能看到通过 XWayland 运行的窗口
+    pub fn foreground() -> Option<WindowInfo> {
+        crate::clipboard_watch::active_window()
+    }
+}
+
+// 从 /proc 读取进程名和可执行文件路径。
+// 其他用户的进程读不到 exe 链接，这时退回到 comm 里的进程名
+#[cfg(target_os = "linux")]
+pub(crate) fn process_image(pid: u32) -> (Option<String>, Option<PathBuf>) {
+    let exe_path = std::fs::read_link(format!("/proc/{}/exe", pid)).ok();
+    let process = match &exe_path {
+        Some(path) => path.file_name().map(|name| name.to_string_lossy().into_owned()),
+        None => std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|comm| comm.trim_end().to_string()),
+    };
+    (process, exe_path)
+}
+
+// 读取窗口所属进程的 PID 和可执行文件路径
+#[cfg(windows)]
+unsafe fn process_image(hwnd: windows::Win32::Foundation::HWND) -> (Option<u32>, Option<PathBuf>) {
+    use windows::{
+        core::PWSTR,
+        Win32::{
+            Foundation::CloseHandle,
+            System::Threading::{
+                OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+                PROCESS_QUERY_LIMITED_INFORMATION,
+            },
+            UI::WindowsAndMessaging::GetWindowThreadProcessId,
+        },
+    };
+
+    let mut pid = 0u32;
+    GetWindowThreadProcessId(hwnd, Some(&mut pid));
+    if pid == 0 {
+        return (None, None);
+    }
+    // 提升了权限的进程可能打不开，这时只能放弃
+    let Ok(process) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) else {
+        return (Some(pid), None);
+    };
+    let mut buffer = [0u16; 1024];
+    let mut size = buffer.len() as u32;
+    let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size);
+    let _ = CloseHandle(process);
+
+    let path = result
+        .ok()
+        .map(|_| PathBuf::from(String::from_utf16_lossy(&buffer[..size as usize])));
+    (Some(pid), path)
+}
+
+/// 捕获到的是什么内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureKind {
+    /// 用户复制或选中的文本
+    #[default]
+    Selection,
+    /// 输入框的全部内容，并不是用户选中的
+    FieldValue,
+}
+
+impl CaptureKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureKind::Selection => "selection",
+            CaptureKind::FieldValue => "field_value",
+        }
+    }
+}
+
+impl std::str::FromStr for CaptureKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CaptureKind, String> {
+        match s {
+            "selection" => Ok(CaptureKind::Selection),
+            "field_value" => Ok(CaptureKind::FieldValue),
+            _ => Err(format!("未知的捕获内容类型: {}", s)),
+        }
+    }
+}
+
+/// 屏幕上的一个矩形，单位是像素
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenRect {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// 选区周围的文本，比如选中的单词所在的句子或段落
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextContext {
+    pub text: String,
+    /// 选区在 `text` 中的起止位置（左闭右开），按字符计算
+    pub start: usize,
+    pub end: usize,
+}
+
+impl TextContext {
+    /// 在这段文本（通常是段落）里找出包含选区的句子。
+    ///
+    /// 句子以中文的句号、问号、叹号，后面跟着空白的 `.`、`?`、`!`，或者换行结束。
+    pub fn sentence(&self) -> TextContext {
+        let chars: Vec<char> = self.text.chars().collect();
+        let start = self.start.min(chars.len());
+        let end = self.end.clamp(start, chars.len());
+        let ends_sentence = |i: usize| match chars[i] {
+            '。' | '？' | '！' | '\n' => true,
+            '.' | '?' | '!' => chars.get(i + 1).is_none_or(|next| next.is_whitespace()),
+            _ => false,
+        };
+
+        let mut from = (0..start).rev().find(|&i| ends_sentence(i)).map_or(0, |i| i + 1);
+        while from < start && chars[from].is_whitespace() {
+            from += 1;
+        }
+        let mut to = (end.saturating_sub(1).max(start)..chars.len())
+            .find(|&i| ends_sentence(i))
+            .map_or(chars.len(), |i| i + 1);
+        while to > end && chars[to - 1].is_whitespace() {
+            to -= 1;
+        }
+
+        TextContext {
+            text: chars[from..to].iter().collect(),
+            start: start - from,
+            end: end - from,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "text": self.text,
+            "start": self.start,
+            "end": self.end,
+        })
+    }
+}
+
+/// 选区中的一段文本。编辑器支持多选区时，一次捕获会有多段
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectedRange {
+    pub text: String,
+    /// 这段文本在屏幕上占据的矩形，跨行时每行一个；控件不提供位置时为空
+    pub rects: Vec<ScreenRect>,
+    /// 选区所在的句子
+    pub sentence: Option<TextContext>,
+    /// 选区所在的段落，跨段落的选区包含所有涉及的段落
+    pub paragraph: Option<TextContext>,
+}
+
+/// 一次捕获的结果
+#[derive(Debug, Clone)]
+pub struct CaptureEvent {
+    /// 内容的文本表示；图片、文件列表等非文本内容见 `mime_type` 和 `data`
+    pub text: String,
+    pub mode: CaptureMode,
+    pub kind: CaptureKind,
+    pub timestamp: SystemTime,
+    pub window: Option<WindowInfo>,
+    /// 原始内容的 MIME 类型，纯文本为 `text/plain`
+    pub mime_type: String,
+    /// 非纯文本内容的原始数据，比如 HTML 源码或 PNG 图片
+    pub data: Option<Vec<u8>>,
+    /// 内容被密码管理器标记为不应记录。这时捕获源不会读取内容，`text` 为空
+    pub sensitive: bool,
+    /// 选区的每一段和它们的位置，目前只有方法二会填写；`text` 是各段用换行连接的结果
+    pub ranges: Vec<SelectedRange>,
+}
+
+impl CaptureEvent {
+    pub fn new(mode: CaptureMode, text: String, window: Option<WindowInfo>) -> CaptureEvent {
+        CaptureEvent {
+            text,
+            mode,
+            kind: CaptureKind::Selection,
+            timestamp: SystemTime::now(),
+            window,
+            mime_type: MIME_TEXT.to_string(),
+            data: None,
+            sensitive: false,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// 由一个或多个选区组成的捕获
+    pub fn from_ranges(mode: CaptureMode, ranges: Vec<SelectedRange>, window: Option<WindowInfo>) -> CaptureEvent {
+        let text = ranges.iter().map(|range| range.text.as_str()).collect::<Vec<_>>().join("\n");
+        CaptureEvent {
+            ranges,
+            ..CaptureEvent::new(mode, text, window)
+        }
+    }
+
+    /// 被标记为敏感、没有读取内容的一次捕获
+    pub fn marked_sensitive(mode: CaptureMode, window: Option<WindowInfo>) -> CaptureEvent {
+        CaptureEvent {
+            sensitive: true,
+            ..CaptureEvent::new(mode, String::new(), window)
+        }
+    }
+
+    /// 转成一个扁平的 JSON 对象，方便用 jq 等工具处理
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "text": self.text,
+            "mode": self.mode.as_str(),
+            "kind": self.kind.as_str(),
+            "timestamp": rfc3339(self.timestamp),
+            "window": self.window.as_ref().map(|w| w.title.as_str()),
+            "process": self.window.as_ref().and_then(|w| w.process.as_deref()),
+            "window_class": self.window.as_ref().and_then(|w| w.class.as_deref()),
+            "pid": self.window.as_ref().and_then(|w| w.pid),
+            "exe_path": self.window.as_ref().and_then(|w| w.exe_path.as_ref()).map(|p| p.to_string_lossy()),
+            "url": self.window.as_ref().and_then(|w| w.url.as_deref()),
+            "mime_type": self.mime_type,
+            "data_size": self.data.as_ref().map(Vec::len),
+            "sensitive": self.sensitive,
+            "ranges": self.ranges.iter().map(|range| serde_json::json!({
+                "text": range.text,
+                "rects": range.rects.iter().map(|rect| serde_json::json!({
+                    "left": rect.left,
+                    "top": rect.top,
+                    "width": rect.width,
+                    "height": rect.height,
+                })).collect::<Vec<_>>(),
+                "sentence": range.sentence.as_ref().map(TextContext::to_json),
+                "paragraph": range.paragraph.as_ref().map(TextContext::to_json),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+// JSON 输出使用的时间格式：本地时区、精确到毫秒的 RFC 3339
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let time: DateTime<Local> = time.into();
+    time.to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+/// 停止信号，可以被克隆后在多个线程之间共享。
+///
+/// 捕获源在循环里检查 `is_stopped`，需要定时等待时用 `wait_timeout` 代替 `thread::sleep`，
+/// 这样停止信号能立即打断等待；如果捕获源阻塞在某个系统调用上
+/// （比如 `GetMessageW`），可以用 `on_stop` 注册一个唤醒回调。
+///
+/// 它同时带有暂停状态：暂停期间捕获源继续运行，但不读取也不发送内容（方法三不会模拟按键）。
+#[derive(Clone, Default)]
+pub struct StopToken {
+    inner: Arc<StopInner>,
+}
+
+#[derive(Default)]
+struct StopInner {
+    stopped: AtomicBool,
+    paused: AtomicBool,
+    callbacks: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
+    // wait_timeout 使用的等待队列
+    wakeup_lock: Mutex<()>,
+    wakeup: Condvar,
+}
+
+impl StopToken {
+    pub fn new() -> StopToken {
+        StopToken::default()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.inner.stopped.load(Ordering::SeqCst)
+    }
+
+    /// 发出停止信号，并依次调用已注册的唤醒回调。重复调用没有副作用。
+    pub fn stop(&self) {
+        if self.inner.stopped.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        {
+            // 持有锁再通知，避免等待方在检查标志和进入等待之间错过通知
+            let _guard = self.inner.wakeup_lock.lock().unwrap();
+            self.inner.wakeup.notify_all();
+        }
+        let callbacks = std::mem::take(&mut *self.inner.callbacks.lock().unwrap());
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    /// 最多等待 `timeout`，期间收到停止信号会立即返回。返回值表示是否已经停止。
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let guard = self.inner.wakeup_lock.lock().unwrap();
+        if self.is_stopped() {
+            return true;
+        }
+        let _ = self
+            .inner
+            .wakeup
+            .wait_timeout_while(guard, timeout, |_| !self.is_stopped())
+            .unwrap();
+        self.is_stopped()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::SeqCst)
+    }
+
+    /// 暂停捕获，可以用 `resume` 恢复
+    pub fn pause(&self) {
+        self.inner.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.inner.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// 注册停止时要执行的回调；如果已经停止，立即执行
+    pub fn on_stop<F>(&self, callback: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut callbacks = self.inner.callbacks.lock().unwrap();
+        if self.is_stopped() {
+            drop(callbacks);
+            callback();
+        } else {
+            callbacks.push(Box::new(callback));
+        }
+    }
+}
+
+/// 所有捕获模式共同实现的接口。
+///
+/// `run` 会阻塞当前线程持续监听，每捕获到一段文本就往 `events` 发送一个事件；
+/// 当 `stop` 被触发、接收端被丢弃（发送失败）或者捕获源自己结束时返回。
+/// 测试时可以用 `ChannelSource` 代替真实的捕获源，直接往里面塞预设的事件。
+pub trait CaptureSource {
+    fn mode(&self) -> CaptureMode;
+    fn run(&mut self, events: &Sender<CaptureEvent>, stop: &StopToken);
+}
+
+/// 从通道里读取事件的捕获源：别的代码往 `input` 里发送事件，它原样转发出去。
+///
+/// 用于测试，或者把其他地方得到的文本接入同一套输出、历史和脱敏流程。
+/// 暂停期间收到的事件直接丢弃；`input` 的发送端全部关闭后捕获源结束。
+pub struct ChannelSource {
+    pub mode: CaptureMode,
+    pub input: Receiver<CaptureEvent>,
+}
+
+impl CaptureSource for ChannelSource {
+    fn mode(&self) -> CaptureMode {
+        self.mode
+    }
+
+    fn run(&mut self, events: &Sender<CaptureEvent>, stop: &StopToken) {
+        while !stop.is_stopped() {
+            match self.input.recv_timeout(Duration::from_millis(50)) {
+                Ok(_) if stop.is_paused() => {}
+                Ok(event) => {
+                    if events.send(event).is_err() {
+                        return;
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// 运行在后台线程中的捕获源
+pub struct CaptureHandle {
+    mode: CaptureMode,
+    stop: StopToken,
+    events: Receiver<CaptureEvent>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl CaptureHandle {
+    pub fn mode(&self) -> CaptureMode {
+        self.mode
+    }
+
+    /// 捕获事件的接收端；捕获源结束后迭代会自然终止
+    pub fn events(&self) -> &Receiver<CaptureEvent> {
+        &self.events
+    }
+
+    /// 获取停止信号的副本，可以交给其他线程（比如控制台信号处理）使用
+    pub fn stop_token(&self) -> StopToken {
+        self.stop.clone()
+    }
+
+    /// 通知捕获源停止，不等待线程退出
+    pub fn stop(&self) {
+        self.stop.stop();
+    }
+
+    /// 暂停捕获，见 `StopToken::pause`
+    pub fn pause(&self) {
+        self.stop.pause();
+    }
+
+    pub fn resume(&self) {
+        self.stop.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.stop.is_paused()
+    }
+
+    /// 等待捕获线程退出
+    pub fn join(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// 通知捕获源停止并等待线程退出
+    pub fn shutdown(self) {
+        self.stop();
+        self.join();
+    }
+}
+
+impl Drop for CaptureHandle {
+    fn drop(&mut self) {
+        // 句柄被丢弃时只发出停止信号，不阻塞调用方
+        self.stop.stop();
+    }
+}
+
+/// 在后台线程启动捕获源
+pub fn start<S>(mut source: S) -> CaptureHandle
+where
+    S: CaptureSource + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let stop = StopToken::new();
+    let mode = source.mode();
+    let thread_stop = stop.clone();
+    let thread = thread::spawn(move || source.run(&tx, &thread_stop));
+    CaptureHandle {
+        mode,
+        stop,
+        events: rx,
+        thread: Some(thread),
+    }
+}
+
+/// 按照原来的控制台格式输出一次捕获
+pub fn print_event(event: &CaptureEvent) {
+    println!("\n--- [{}] ---", event.mode.banner());
+    if let Some(window) = &event.window {
+        match &window.process {
+            Some(process) => println!("[来源窗口] {} ({})", window.title, process),
+            None => println!("[来源窗口] {}", window.title),
+        }
+        if let Some(url) = &window.url {
+            println!("[网址] {}", url);
+        }
+    }
+    if event.mime_type != MIME_TEXT {
+        println!("[格式] {}", event.mime_type);
+    }
+    if event.kind == CaptureKind::FieldValue {
+        println!("[输入框内容]");
+    }
+    if event.ranges.len() > 1 {
+        println!("[选区] 共 {} 段", event.ranges.len());
+    }
+    println!("{}", event.text);
+    if let [range] = event.ranges.as_slice() {
+        if let Some(sentence) = range.sentence.as_ref().filter(|s| s.text.trim() != range.text.trim()) {
+            println!("[所在句子] {}", sentence.text);
+        }
+    }
+    println!("--- [内容结束] ---\n");
+}
+
+/// 捕获结果在标准输出上的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// 带标题横幅的可读文本
+    #[default]
+    Text,
+    /// 每个捕获一行 JSON（JSON Lines），诊断信息只写到标准错误
+    Jsonl,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OutputFormat, String> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            _ => Err(format!("未知的输出格式: {}（可选 text、jsonl）", s)),
+        }
+    }
+}
+
+/// 控制台（菜单或命令行）运行时如何处理捕获结果
+#[derive(Debug, Clone)]
+pub struct ConsoleOptions {
+    /// 是否写入历史数据库
+    pub save_history: bool,
+    pub format: OutputFormat,
+    /// 输出和保存之前的脱敏处理
+    pub redactor: Redactor,
+    /// 在这个地址上启动本地接口（见 `server`），把捕获推送给其他程序
+    pub serve: Option<SocketAddr>,
+}
+
+impl Default for ConsoleOptions {
+    fn default() -> Self {
+        ConsoleOptions {
+            save_history: true,
+            format: OutputFormat::Text,
+            redactor: Redactor::default(),
+            serve: None,
+        }
+    }
+}
+
+// 控制台模式的事件消费：打印到控制台并按需写入历史数据库
+fn print_and_record(handle: CaptureHandle, options: &ConsoleOptions) {
+    let history = if options.save_history {
+        match HistoryStore::open_default() {
+            Ok(history) => Some(history),
+            Err(e) => {
+                eprintln!("[警告] 无法打开历史数据库，本次捕获不会被保存: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let server = options.serve.and_then(|addr| {
+        match ApiServer::start(addr, handle.mode(), handle.stop_token()) {
+            Ok(server) => {
+                eprintln!("[状态] 本地接口已启动: http://{}/api/", server.local_addr());
+                Some(server)
+            }
+            Err(e) => {
+                eprintln!("[警告] 无法启动本地接口（{}）: {}", addr, e);
+                None
+            }
+        }
+    });
+    for mut event in handle.events() {
+        // 暂停之前已经发出的事件也不再处理
+        if handle.is_paused() {
+            continue;
+        }
+        match options.redactor.redact_event(&mut event) {
+            Redacted::Clean => {}
+            Redacted::Masked(labels) => eprintln!("[脱敏] 已隐藏: {}", labels.join("、")),
+            Redacted::Dropped(label) => {
+                eprintln!("[脱敏] 捕获内容包含{}，已丢弃。", label);
+                continue;
+            }
+        }
+        match options.format {
+            OutputFormat::Text => print_event(&event),
+            OutputFormat::Jsonl => println!("{}", event.to_json()),
+        }
+        let id = history.as_ref().and_then(|history| {
+            history
+                .insert(&event)
+                .map_err(|e| eprintln!("[警告] 保存捕获历史失败: {}", e))
+                .ok()
+        });
+        if let Some(server) = &server {
+            let mut json = event.to_json();
+            json["id"] = id.into();
+            server.publish(&json);
+        }
+    }
+    drop(server);
+    handle.join();
+}
+
+/// 启动捕获源并把所有事件打印到控制台、写入历史数据库，直到捕获源结束。
+/// 与菜单里的其他模式一样使用 `redact.toml` 里的脱敏设置
+pub fn run_and_print<S>(source: S)
+where
+    S: CaptureSource + Send + 'static,
+{
+    let options = ConsoleOptions {
+        redactor: redact::load_default_or_builtin(),
+        ..ConsoleOptions::default()
+    };
+    run_to_console(source, &options);
+}
+
+/// 与 `run_and_print` 相同，但可以指定输出选项
+pub fn run_to_console<S>(source: S, options: &ConsoleOptions)
+where
+    S: CaptureSource + Send + 'static,
+{
+    print_and_record(start(source), options);
+}
+
+/// 启动捕获源并把事件打印到控制台、写入历史数据库，用户按下回车后停止捕获并返回。
+///
+/// 供交互式菜单使用：停止的是捕获线程而不是整个进程，所以可以回到菜单继续选择。
+pub fn run_until_enter<S>(source: S)
+where
+    S: CaptureSource + Send + 'static,
+{
+    let handle = start(source);
+    let stop = handle.stop_token();
+    let options = ConsoleOptions {
+        redactor: redact::load_default_or_builtin(),
+        ..ConsoleOptions::default()
+    };
+    let printer = thread::spawn(move || print_and_record(handle, &options));
+
+    let mut line = String::new();
+    let _ = io::stdin().read_line(&mut line);
+
+    eprintln!("[状态] 正在停止捕获...");
+    stop.stop();
+    let _ = printer.join();
+    eprintln!("[状态] 捕获已停止，返回菜单。");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(text: &str) -> CaptureEvent {
+        CaptureEvent::new(CaptureMode::ClipboardPoll, text.to_string(), None)
+    }
+
+    fn start_channel() -> (Sender<CaptureEvent>, CaptureHandle) {
+        let (input, rx) = mpsc::channel();
+        let handle = start(ChannelSource {
+            mode: CaptureMode::ClipboardPoll,
+            input: rx,
+        });
+        (input, handle)
+    }
+
+    #[test]
+    fn delivers_events_in_order() {
+        let (input, handle) = start_channel();
+        assert_eq!(handle.mode(), CaptureMode::ClipboardPoll);
+        input.send(event("第一段")).unwrap();
+        input.send(event("第二段")).unwrap();
+        drop(input);
+
+        // 输入关闭后捕获源结束，迭代自然终止
+        let texts: Vec<String> = handle.events().iter().map(|event| event.text).collect();
+        assert_eq!(texts, ["第一段", "第二段"]);
+        handle.join();
+    }
+
+    #[test]
+    fn stop_ends_source_while_input_is_open() {
+        let (input, handle) = start_channel();
+        let stop = handle.stop_token();
+        assert!(!stop.is_stopped());
+        handle.shutdown();
+        assert!(stop.is_stopped());
+        // 捕获源已经退出，接收端也跟着释放了
+        assert!(input.send(event("太晚了")).is_err());
+    }
+
+    #[test]
+    fn drops_events_while_paused() {
+        let (input, handle) = start_channel();
+        handle.pause();
+        assert!(handle.is_paused());
+        input.send(event("暂停时复制的")).unwrap();
+        // 等捕获源在暂停状态下取走这个事件，再恢复
+        thread::sleep(Duration::from_millis(300));
+        handle.resume();
+        assert!(!handle.is_paused());
+        input.send(event("恢复后复制的")).unwrap();
+
+        let received = handle.events().recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(received.text, "恢复后复制的");
+        handle.shutdown();
+    }
+
+    #[test]
+    fn stop_token_wakes_waiters_and_runs_callbacks() {
+        let stop = StopToken::new();
+        assert!(!stop.wait_timeout(Duration::from_millis(1)));
+
+        let called = Arc::new(AtomicBool::new(false));
+        let flag = called.clone();
+        stop.on_stop(move || flag.store(true, Ordering::SeqCst));
+        let waiter = {
+            let stop = stop.clone();
+            thread::spawn(move || stop.wait_timeout(Duration::from_secs(30)))
+        };
+        stop.stop();
+        assert!(waiter.join().unwrap());
+        assert!(called.load(Ordering::SeqCst));
+
+        // 已经停止时注册的回调立即执行
+        let late = Arc::new(AtomicBool::new(false));
+        let flag = late.clone();
+        stop.on_stop(move || flag.store(true, Ordering::SeqCst));
+        assert!(late.load(Ordering::SeqCst));
+    }
+
+    fn context(text: &str, start: usize, end: usize) -> TextContext {
+        TextContext {
+            text: text.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn sentence_ends_at_chinese_punctuation() {
+        // 选中了“二”
+        let sentence = context("第一句。第二句？第三句！", 5, 6).sentence();
+        assert_eq!(sentence, context("第二句？", 1, 2));
+        let sentence = context("第一行\n第二行", 4, 5).sentence();
+        assert_eq!(sentence, context("第二行", 0, 1));
+    }
+
+    #[test]
+    fn period_needs_following_whitespace() {
+        // “3.14” 里的点不是句子结尾
+        let sentence = context("Pi is 3.14 exactly. Next one.", 6, 10).sentence();
+        assert_eq!(sentence, context("Pi is 3.14 exactly.", 6, 10));
+        // 句子开头的空白不算在内
+        let sentence = context("Pi is 3.14 exactly. Next one.", 20, 24).sentence();
+        assert_eq!(sentence, context("Next one.", 0, 4));
+    }
+
+    #[test]
+    fn selection_across_sentences_keeps_both() {
+        let sentence = context("第一句。第二句。第三句。", 2, 6).sentence();
+        assert_eq!(sentence, context("第一句。第二句。", 2, 6));
+    }
+
+    #[test]
+    fn sentence_of_empty_or_out_of_range_selection() {
+        assert_eq!(context("", 0, 0).sentence(), context("", 0, 0));
+        // 越界的起点收到末尾，终点不会在起点之前
+        assert_eq!(context("abc", 5, 2).sentence(), context("abc", 3, 3));
+    }
+}
@@ -0,0 +1,360 @@
+// --- 剪贴板内容 ---
+// 剪贴板里不只有纯文本：网页和 Office 复制出来的是 HTML，写字板之类的程序只提供 RTF，
+// 截图是图片，资源管理器里复制的是文件列表。
+// 这里把各种格式统一成 ClipboardContent。每种内容都有一个可读、可检索的文本表示，
+// 非纯文本的内容另外保留原始数据（HTML 或 RTF 源码、PNG 图片、文件 URI 列表）和它的 MIME 类型。
+//
+// RTF 只在 Windows 和 Wayland 上读取：X11 下 arboard 读不到 text/rtf，这时退回到纯文本。
+
+use crate::capture::{CaptureEvent, CaptureMode, WindowInfo};
+use arboard::Clipboard;
+use image::{codecs::png::PngEncoder, ExtendedColorType, ImageEncoder};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+pub const MIME_TEXT: &str = "text/plain";
+pub const MIME_HTML: &str = "text/html";
+pub const MIME_RTF: &str = "text/rtf";
+pub const MIME_PNG: &str = "image/png";
+pub const MIME_URI_LIST: &str = "text/uri-list";
+
+/// 一次复制的内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardContent {
+    Text(String),
+    /// 富文本。`text` 是同一次复制里附带的纯文本（如果有）
+    Html { html: String, text: Option<String> },
+    /// RTF 富文本，同样附带纯文本（如果有）
+    Rtf { rtf: String, text: Option<String> },
+    /// 图片，统一编码成 PNG
+    Image { width: u32, height: u32, png: Vec<u8> },
+    /// 文件列表
+    Files(Vec<PathBuf>),
+}
+
+impl ClipboardContent {
+    /// 从剪贴板读取内容。
+    ///
+    /// 优先级：文件列表、HTML、RTF、纯文本、图片。Office 之类的程序复制文字时会顺带放一张位图，
+    /// 所以只有在没有任何文本时才把内容当作图片。剪贴板为空时返回 None。
+    pub fn read(clipboard: &mut Clipboard) -> Option<ClipboardContent> {
+        if let Ok(files) = clipboard.get().file_list() {
+            if !files.is_empty() {
+                return Some(ClipboardContent::Files(files));
+            }
+        }
+
+        let text = clipboard.get_text().ok().filter(|text| !text.is_empty());
+        if let Ok(html) = clipboard.get().html() {
+            if !html.trim().is_empty() {
+                return Some(ClipboardContent::Html { html, text });
+            }
+        }
+        #[cfg(windows)]
+        if let Some(rtf) = crate::clipboard_watch::clipboard_rtf().filter(|rtf| !rtf.trim().is_empty()) {
+            return Some(ClipboardContent::Rtf { rtf, text });
+        }
+        if let Some(text) = text {
+            return Some(ClipboardContent::Text(text));
+        }
+
+        let image = clipboard.get_image().ok()?;
+        match encode_png(image.width as u32, image.height as u32, &image.bytes) {
+            Ok(png) => Some(ClipboardContent::Image {
+                width: image.width as u32,
+                height: image.height as u32,
+                png,
+            }),
+            Err(e) => {
+                eprintln!("[警告] 图片编码失败: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 剪贴板内容的指纹，轮询时用来判断内容有没有变化。
+    ///
+    /// 按和 `read` 相同的优先级取内容，但图片只对原始像素求哈希，不编码成 PNG，
+    /// 图片一直留在剪贴板上时每次轮询也不会重新编码。剪贴板为空时返回 None。
+    pub fn fingerprint(clipboard: &mut Clipboard) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        let files = clipboard.get().file_list().ok().filter(|files| !files.is_empty());
+        let text = clipboard.get_text().ok().filter(|text| !text.is_empty());
+        #[cfg(windows)]
+        let rtf = crate::clipboard_watch::clipboard_rtf().filter(|rtf| !rtf.trim().is_empty());
+        #[cfg(not(windows))]
+        let rtf: Option<String> = None;
+        if let Some(files) = files {
+            files.hash(&mut hasher);
+        } else if let Some(html) = clipboard.get().html().ok().filter(|html| !html.trim().is_empty()) {
+            (MIME_HTML, html, text).hash(&mut hasher);
+        } else if let Some(rtf) = rtf {
+            (MIME_RTF, rtf, text).hash(&mut hasher);
+        } else if let Some(text) = text {
+            (MIME_TEXT, text).hash(&mut hasher);
+        } else {
+            let image = clipboard.get_image().ok()?;
+            (MIME_PNG, image.width, image.height, &image.bytes[..]).hash(&mut hasher);
+        }
+        Some(hasher.finish())
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ClipboardContent::Text(_) => MIME_TEXT,
+            ClipboardContent::Html { .. } => MIME_HTML,
+            ClipboardContent::Rtf { .. } => MIME_RTF,
+            ClipboardContent::Image { .. } => MIME_PNG,
+            ClipboardContent::Files(_) => MIME_URI_LIST,
+        }
+    }
+
+    /// 可读的文本表示，用于控制台输出和全文检索
+    pub fn text(&self) -> String {
+        match self {
+            ClipboardContent::Text(text) => text.clone(),
+            ClipboardContent::Html { html, text } => text.clone().unwrap_or_else(|| strip_tags(html)),
+            ClipboardContent::Rtf { rtf, text } => text.clone().unwrap_or_else(|| strip_rtf(rtf)),
+            ClipboardContent::Image { width, height, .. } => format!("[图片 {}x{}]", width, height),
+            ClipboardContent::Files(files) => files
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// 原始数据；纯文本没有额外的数据
+    pub fn data(&self) -> Option<Vec<u8>> {
+        match self {
+            ClipboardContent::Text(_) => None,
+            ClipboardContent::Html { html, .. } => Some(html.as_bytes().to_vec()),
+            ClipboardContent::Rtf { rtf, .. } => Some(rtf.as_bytes().to_vec()),
+            ClipboardContent::Image { png, .. } => Some(png.clone()),
+            ClipboardContent::Files(files) => Some(to_uri_list(files).into_bytes()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            ClipboardContent::Text(text) => text.is_empty(),
+            ClipboardContent::Html { html, .. } => html.trim().is_empty(),
+            ClipboardContent::Rtf { rtf, .. } => rtf.trim().is_empty(),
+            ClipboardContent::Image { png, .. } => png.is_empty(),
+            ClipboardContent::Files(files) => files.is_empty(),
+        }
+    }
+
+    /// 生成一次捕获事件
+    pub fn into_event(self, mode: CaptureMode, window: Option<WindowInfo>) -> CaptureEvent {
+        let mut event = CaptureEvent::new(mode, self.text(), window);
+        event.mime_type = self.mime_type().to_string();
+        event.data = self.data();
+        event
+    }
+}
+
+/// 把 RGBA 像素编码成 PNG
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> image::ImageResult<Vec<u8>> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).write_image(rgba, width, height, ExtendedColorType::Rgba8)?;
+    Ok(png)
+}
+
+/// 从 PNG 文件头读出宽和高
+pub fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+    // 8 字节签名之后紧跟 IHDR 块：4 字节长度、4 字节类型、4 字节宽、4 字节高
+    if png.len() < 24 || &png[..8] != b"\x89PNG\r\n\x1a\n" || &png[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(png[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// 解析 `text/uri-list`（RFC 2483），只保留本地文件
+pub fn parse_uri_list(list: &str) -> Vec<PathBuf> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.strip_prefix("file://"))
+        // file://host/path 里的 host 通常为空或是 localhost
+        .map(|rest| rest.strip_prefix("localhost").unwrap_or(rest))
+        .map(|path| PathBuf::from(percent_decode(path)))
+        .collect()
+}
+
+fn to_uri_list(files: &[PathBuf]) -> String {
+    files
+        .iter()
+        .map(|path| format!("file://{}\r\n", percent_encode(path)))
+        .collect()
+}
+
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(byte) = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn percent_encode(path: &Path) -> String {
+    // Windows 路径统一成正斜杠，盘符前补一个斜杠：C:\a → /C:/a
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = if path.starts_with('/') { path } else { format!("/{}", path) };
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~:".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// 没有附带纯文本的 HTML，粗略去掉标签作为文本表示
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// 这些目标组里是字体表、颜色表、文档属性、图片数据等，不是正文
+const RTF_SKIPPED_GROUPS: &[&str] = &["fonttbl", "colortbl", "stylesheet", "info", "pict", "header", "footer"];
+
+// 没有附带纯文本的 RTF，粗略取出其中的文字作为文本表示：去掉控制字和字体表等目标组，
+// \par 换行，\uN 按 Unicode 还原。\'hh 按 Latin-1 还原，依赖代码页的中文只有写成 \uN 时才能正确还原
+fn strip_rtf(rtf: &str) -> String {
+    let mut text = RtfText::default();
+    let mut chars = rtf.chars().peekable();
+    // 外层组的跳过状态，遇到 } 时恢复
+    let mut groups: Vec<bool> = Vec::new();
+    let mut skipping = false;
+    // \uN 之后跟着的替代字符个数（\ucN）
+    let mut fallback_len = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => groups.push(skipping),
+            '}' => skipping = groups.pop().unwrap_or(false),
+            '\r' | '\n' => {}
+            '\\' => match chars.next() {
+                Some(c @ ('\\' | '{' | '}')) => text.push(skipping, c),
+                Some('*') => skipping = true,
+                Some('~') => text.push(skipping, ' '),
+                Some('\'') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                        text.push(skipping, byte as char);
+                    }
+                }
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let mut word = String::from(c);
+                    while let Some(c) = chars.next_if(char::is_ascii_alphabetic) {
+                        word.push(c);
+                    }
+                    let mut param = String::new();
+                    if let Some(c) = chars.next_if_eq(&'-') {
+                        param.push(c);
+                    }
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        param.push(c);
+                    }
+                    // 控制字后面的一个空格是分隔符，不是正文
+                    chars.next_if_eq(&' ');
+                    let param: Option<i32> = param.parse().ok();
+                    match word.as_str() {
+                        "par" | "line" => text.push(skipping, '\n'),
+                        "tab" => text.push(skipping, '\t'),
+                        "uc" => fallback_len = param.unwrap_or(1).max(0) as usize,
+                        "u" => {
+                            // 参数是有符号的 16 位整数，大于 32767 的码位写成负数
+                            let code = param.map(|n| if n < 0 { n + 65536 } else { n });
+                            if let Some(c) = code.and_then(|n| char::from_u32(n as u32)) {
+                                text.push(skipping, c);
+                            }
+                            if !skipping {
+                                text.fallback_left = fallback_len;
+                            }
+                        }
+                        word if RTF_SKIPPED_GROUPS.contains(&word) => skipping = true,
+                        _ => {}
+                    }
+                }
+                // 其他控制符号（\- 可选连字符等）没有对应的文字
+                _ => {}
+            },
+            c => text.push(skipping, c),
+        }
+    }
+    text.text.trim().to_string()
+}
+
+// strip_rtf 的输出
+#[derive(Default)]
+struct RtfText {
+    text: String,
+    // 还要跳过几个 \uN 的替代字符
+    fallback_left: usize,
+}
+
+impl RtfText {
+    fn push(&mut self, skipping: bool, c: char) {
+        if skipping {
+            return;
+        }
+        if self.fallback_left > 0 {
+            self.fallback_left -= 1;
+        } else {
+            self.text.push(c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_rtf_to_text() {
+        let rtf = r"{\rtf1\ansi\deff0{\fonttbl{\f0 Calibri;}}{\colortbl;\red255\green0\blue0;}
+{\*\generator Riched20;}\f0\fs22 Hello \b bold\b0 , \{x\}\par
+\uc1\u20013?\u25991?\tab caf\'e9}";
+        assert_eq!(strip_rtf(rtf), "Hello bold, {x}\n中文\tcafé");
+    }
+
+    #[test]
+    fn rtf_content_prefers_plain_text() {
+        let content = ClipboardContent::Rtf {
+            rtf: r"{\rtf1 x}".to_string(),
+            text: Some("纯文本".to_string()),
+        };
+        assert_eq!(content.text(), "纯文本");
+        assert_eq!(content.mime_type(), MIME_RTF);
+        assert_eq!(content.data().unwrap(), br"{\rtf1 x}");
+        assert_eq!(strip_rtf(""), "");
+    }
+}
@@ -0,0 +1,242 @@
+// --- 方法一：监听剪贴板 ---
+// 这是最简单、最稳定的方法。
+// 优先使用系统的剪贴板变化通知（见 clipboard_watch），通知不可用时回退到定时轮询。
+// 除了纯文本，也会捕获 HTML、RTF、图片和文件列表（见 clipboard_content）。
+
+use crate::{
+    capture::{self, CaptureEvent, CaptureHandle, CaptureMode, CaptureSource, StopToken, WindowInfo},
+    clipboard_content::ClipboardContent,
+    clipboard_watch::{self, ChangeCounter, ClipboardWatcher, WaitOutcome, WatchError},
+    rules::{self, RuleSet},
+};
+use arboard::Clipboard;
+use std::{
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
+
+/// 如何发现剪贴板变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChangeDetection {
+    /// 优先使用系统通知，不可用时回退到轮询
+    #[default]
+    Auto,
+    /// 始终定时轮询
+    Polling,
+}
+
+/// 剪贴板捕获源：剪贴板内容变化时产生一次捕获。
+///
+/// `interval` 是轮询模式下的检查间隔；使用系统通知时，
+/// 同样的内容在一个间隔内的重复通知会被视为同一次复制。
+pub struct ClipboardPoller {
+    pub interval: Duration,
+    pub detection: ChangeDetection,
+    /// 被拒绝的窗口里复制的内容会被丢弃
+    pub rules: RuleSet,
+}
+
+impl Default for ClipboardPoller {
+    fn default() -> Self {
+        ClipboardPoller {
+            interval: Duration::from_millis(500), // 每 500 毫秒检查一次
+            detection: ChangeDetection::Auto,
+            rules: RuleSet::default(),
+        }
+    }
+}
+
+// 复制内容的来源窗口：复制通常发生在前台窗口里。其他平台暂时拿不到
+fn source_window() -> Option<WindowInfo> {
+    #[cfg(any(windows, target_os = "linux"))]
+    {
+        WindowInfo::foreground()
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        None
+    }
+}
+
+// 一个监听循环为什么结束
+enum LoopExit {
+    // 收到停止信号或接收端已关闭
+    Finished,
+    // 通知后端出错，需要回退到轮询
+    WatcherFailed(WatchError),
+}
+
+impl ClipboardPoller {
+    // 按规则检查来源窗口后发送一次捕获，`None` 表示内容被密码管理器标记过、没有读取；
+    // 返回 false 表示接收端已关闭
+    fn emit(&self, content: Option<ClipboardContent>, events: &Sender<CaptureEvent>) -> bool {
+        let window = source_window();
+        if !self.rules.allows(window.as_ref()) {
+            if let Some(window) = &window {
+                eprintln!("[规则] 已忽略来自 {} 的复制。", window.title);
+            }
+            return true;
+        }
+        // 内容已经读完了，这时再去读浏览器网址不会耽误复制
+        #[cfg(windows)]
+        let window = window.map(WindowInfo::with_foreground_url);
+        let event = match content {
+            Some(content) => content.into_event(self.mode(), window),
+            None => CaptureEvent::marked_sensitive(self.mode(), window),
+        };
+        events.send(event).is_ok()
+    }
+
+    // 基于系统通知的监听循环：每次通知读取一次剪贴板
+    fn run_notified(
+        &self,
+        watcher: &mut dyn ClipboardWatcher,
+        mut clipboard: Option<&mut Clipboard>,
+        events: &Sender<CaptureEvent>,
+        stop: &StopToken,
+    ) -> LoopExit {
+        let mut previous: Option<(Option<ClipboardContent>, Instant)> = None;
+        loop {
+            match watcher.wait_for_change(stop, self.interval) {
+                Ok(WaitOutcome::Stopped) => return LoopExit::Finished,
+                Ok(WaitOutcome::Timeout) => continue,
+                Ok(WaitOutcome::Changed) => {}
+                Err(e) => return LoopExit::WatcherFailed(e),
+            }
+            // 暂停期间的复制直接忽略
+            if stop.is_paused() {
+                continue;
+            }
+
+            // 密码管理器标记过的内容不读取，记作 None
+            let current = if watcher.marked_sensitive() {
+                None
+            } else {
+                // 有的后端（Wayland）在通知时已经读好了内容，其余后端需要自己读取
+                let content = match watcher.take_content() {
+                    Some(content) => content,
+                    None => clipboard.as_deref_mut().and_then(ClipboardContent::read),
+                };
+                let Some(content) = content.filter(|content| !content.is_empty()) else {
+                    continue;
+                };
+                Some(content)
+            };
+            // 剪贴板管理器接管内容、程序分多次写入不同格式时，一次复制会触发多次通知
+            let now = Instant::now();
+            let duplicate = previous
+                .as_ref()
+                .map(|(content, at)| *content == current && now.duration_since(*at) < self.interval)
+                .unwrap_or(false);
+            if duplicate {
+                continue;
+            }
+
+            if !self.emit(current.clone(), events) {
+                return LoopExit::Finished;
+            }
+            previous = Some((current, now));
+        }
+    }
+
+    // 定时轮询的监听循环。每次先比较廉价的变化标记，变了才完整读取（图片要编码成 PNG）
+    fn run_polling(&self, clipboard: &mut Clipboard, events: &Sender<CaptureEvent>, stop: &StopToken) {
+        let mut counter = ChangeCounter::open()
+            .map_err(|e| eprintln!("[状态] 剪贴板变化计数不可用（{}），改为比较内容指纹。", e))
+            .ok();
+        let mut signature = change_signature(&mut counter, clipboard);
+        let mut previous = ClipboardContent::read(clipboard);
+
+        while !stop.is_stopped() {
+            let current_signature = change_signature(&mut counter, clipboard);
+            let current = if current_signature != signature {
+                signature = current_signature;
+                ClipboardContent::read(clipboard)
+            } else {
+                None
+            };
+            // 计数变了内容却可能没变（比如重新复制了同样的内容），所以仍然和上一次的内容比较
+            if let Some(content) = current.as_ref().filter(|c| !c.is_empty() && previous.as_ref() != Some(*c)) {
+                // 暂停期间只记下内容，恢复后不会补发
+                if !stop.is_paused() {
+                    // 轮询时内容已经读出来了，但被标记过的仍然不往外发
+                    let content = (!clipboard_watch::clipboard_marked_sensitive()).then(|| content.clone());
+                    if !self.emit(content, events) {
+                        break;
+                    }
+                }
+                previous = current;
+            }
+            // 等待下一次检查，收到停止信号时立即结束
+            if stop.wait_timeout(self.interval) {
+                break;
+            }
+        }
+    }
+}
+
+// 轮询时判断剪贴板有没有变化的标记：优先用变化计数，读取失败后改用内容指纹。
+// 两种标记混在一起比较时只会多读一次，读出来的内容还要和上一次比较，不会重复捕获
+fn change_signature(counter: &mut Option<ChangeCounter>, clipboard: &mut Clipboard) -> Option<u64> {
+    if let Some(current) = counter.as_mut() {
+        match current.current() {
+            Ok(value) => return Some(value),
+            Err(e) => {
+                eprintln!("[警告] 读取剪贴板变化计数失败（{}），改为比较内容指纹。", e);
+                *counter = None;
+            }
+        }
+    }
+    ClipboardContent::fingerprint(clipboard)
+}
+
+impl CaptureSource for ClipboardPoller {
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::ClipboardPoll
+    }
+
+    fn run(&mut self, events: &Sender<CaptureEvent>, stop: &StopToken) {
+        // Wayland 下后台程序可能无法初始化剪贴板，但 data-control 后端会自己读取内容，
+        // 所以这里先不报错，等确定需要轮询时再说
+        let mut clipboard = Clipboard::new();
+
+        if self.detection == ChangeDetection::Auto {
+            match clipboard_watch::open_clipboard_watcher() {
+                Ok(mut watcher) => {
+                    eprintln!("[状态] 使用 {} 接收剪贴板变化通知。", watcher.name());
+                    match self.run_notified(watcher.as_mut(), clipboard.as_mut().ok(), events, stop) {
+                        LoopExit::Finished => return,
+                        LoopExit::WatcherFailed(e) => {
+                            eprintln!("[警告] 剪贴板变化通知中断（{}），回退到轮询。", e);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("[状态] 剪贴板变化通知不可用（{}），回退到轮询。", e),
+            }
+        }
+
+        match clipboard {
+            Ok(mut clipboard) => self.run_polling(&mut clipboard, events, stop),
+            Err(e) => eprintln!("[错误] 无法初始化剪贴板: {:?}", e),
+        }
+    }
+}
+
+/// 在后台线程启动剪贴板监听，通过返回的句柄读取事件或停止。
+/// `interval` 是通知不可用时的轮询间隔。
+pub fn start(interval: Duration) -> CaptureHandle {
+    capture::start(ClipboardPoller {
+        interval,
+        ..ClipboardPoller::default()
+    })
+}
+
+pub fn run() {
+    println!("方法一：剪贴板监听模式已启动。");
+    println!("请在任何地方复制文本、图片或文件 (Ctrl+C)，这里会显示出来。按回车键停止并返回菜单。");
+
+    capture::run_until_enter(ClipboardPoller {
+        rules: rules::load_default_or_builtin(),
+        ..ClipboardPoller::default()
+    });
+}
@@ -0,0 +1,174 @@
+// --- 剪贴板快照（Windows） ---
+// 方法三要借用剪贴板来读取选中文本，用完必须原样还给用户。
+// 只备份纯文本是不够的：用户剪贴板里的图片、HTML、文件列表会被覆盖掉。
+// 这里直接用 Win32 剪贴板 API 枚举所有格式，把每种格式的数据复制一份，恢复时全部放回去。
+
+use std::{thread, time::Duration};
+use windows::{
+    core::Result,
+    Win32::{
+        Foundation::{GlobalFree, HANDLE, HGLOBAL},
+        System::{
+            DataExchange::{
+                CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData, OpenClipboard,
+                SetClipboardData,
+            },
+            Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE},
+        },
+    },
+};
+
+const CF_UNICODETEXT: u32 = 13;
+
+// 这些格式的数据是 GDI 句柄而不是内存块，无法按字节复制。
+// CF_BITMAP 会由系统根据备份的 CF_DIB 重新合成，位图不会丢；图元文件则无法恢复
+const CF_BITMAP: u32 = 2;
+const CF_METAFILEPICT: u32 = 3;
+const CF_PALETTE: u32 = 9;
+const CF_ENHMETAFILE: u32 = 14;
+const CF_OWNERDISPLAY: u32 = 0x0080;
+const CF_DSPBITMAP: u32 = 0x0082;
+const CF_DSPMETAFILEPICT: u32 = 0x0083;
+const CF_DSPENHMETAFILE: u32 = 0x008E;
+const CF_GDIOBJFIRST: u32 = 0x0300;
+const CF_GDIOBJLAST: u32 = 0x03FF;
+
+// 别的程序可能正占用剪贴板，打开失败时重试几次
+const OPEN_ATTEMPTS: u32 = 10;
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// 剪贴板上所有可复制格式的完整副本
+#[derive(Debug, Clone, Default)]
+pub struct ClipboardSnapshot {
+    formats: Vec<(u32, Vec<u8>)>,
+}
+
+impl ClipboardSnapshot {
+    /// 复制剪贴板上当前的所有格式
+    pub fn capture() -> Result<ClipboardSnapshot> {
+        let _guard = OpenGuard::open()?;
+        let mut formats = Vec::new();
+        let mut format = 0;
+        unsafe {
+            loop {
+                format = EnumClipboardFormats(format);
+                if format == 0 {
+                    break;
+                }
+                if !is_copyable(format) {
+                    continue;
+                }
+                // 延迟渲染的格式在这里才真正生成，生成失败的格式直接跳过
+                if let Ok(handle) = GetClipboardData(format) {
+                    if let Some(data) = read_global(handle) {
+                        formats.push((format, data));
+                    }
+                }
+            }
+        }
+        Ok(ClipboardSnapshot { formats })
+    }
+
+    /// 清空剪贴板并放回快照中的所有格式；快照为空时只清空
+    pub fn restore(&self) -> Result<()> {
+        let _guard = OpenGuard::open()?;
+        unsafe {
+            EmptyClipboard()?;
+            for (format, data) in &self.formats {
+                let memory = write_global(data)?;
+                // 成功后内存归系统所有，失败时要自己释放
+                if let Err(e) = SetClipboardData(*format, HANDLE(memory.0 as isize)) {
+                    let _ = GlobalFree(memory);
+                    eprintln!("[警告] 恢复剪贴板格式 {} 失败: {}", format, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.formats.is_empty()
+    }
+
+    /// 快照中的格式数量
+    pub fn len(&self) -> usize {
+        self.formats.len()
+    }
+
+    /// 快照中的 Unicode 文本
+    pub fn text(&self) -> Option<String> {
+        let (_, data) = self.formats.iter().find(|(format, _)| *format == CF_UNICODETEXT)?;
+        let wide: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|&c| c != 0)
+            .collect();
+        Some(String::from_utf16_lossy(&wide))
+    }
+}
+
+fn is_copyable(format: u32) -> bool {
+    !matches!(
+        format,
+        CF_BITMAP
+            | CF_METAFILEPICT
+            | CF_PALETTE
+            | CF_ENHMETAFILE
+            | CF_OWNERDISPLAY
+            | CF_DSPBITMAP
+            | CF_DSPMETAFILEPICT
+            | CF_DSPENHMETAFILE
+            | CF_GDIOBJFIRST..=CF_GDIOBJLAST
+    )
+}
+
+pub(crate) unsafe fn read_global(handle: HANDLE) -> Option<Vec<u8>> {
+    let memory = HGLOBAL(handle.0 as *mut _);
+    let size = GlobalSize(memory);
+    let ptr = GlobalLock(memory) as *const u8;
+    if ptr.is_null() {
+        return None;
+    }
+    let data = std::slice::from_raw_parts(ptr, size).to_vec();
+    let _ = GlobalUnlock(memory);
+    Some(data)
+}
+
+unsafe fn write_global(data: &[u8]) -> Result<HGLOBAL> {
+    let memory = GlobalAlloc(GMEM_MOVEABLE, data.len().max(1))?;
+    let ptr = GlobalLock(memory) as *mut u8;
+    if ptr.is_null() {
+        let _ = GlobalFree(memory);
+        return Err(windows::core::Error::from_win32());
+    }
+    std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+    let _ = GlobalUnlock(memory);
+    Ok(memory)
+}
+
+// 打开剪贴板，离开作用域时自动关闭
+pub(crate) struct OpenGuard;
+
+impl OpenGuard {
+    pub(crate) fn open() -> Result<OpenGuard> {
+        let mut attempt = 1;
+        loop {
+            match unsafe { OpenClipboard(None) } {
+                Ok(()) => return Ok(OpenGuard),
+                Err(e) if attempt >= OPEN_ATTEMPTS => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    thread::sleep(OPEN_RETRY_DELAY);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for OpenGuard {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseClipboard();
+        }
+    }
+}
@@ -0,0 +1,301 @@
+// --- 剪贴板变化通知 ---
+// 轮询需要定时读取整个剪贴板：浪费 CPU，间隔内被覆盖的复制会丢失，还会有最多一个间隔的延迟。
+// 这里改为由系统通知"剪贴板变了"：
+// - Linux (X11)：XFixes 扩展的 selection owner 变更事件
+// - Linux (Wayland)：ext-data-control-v1 / wlr-data-control-unstable-v1 的 selection 事件
+// - Windows：AddClipboardFormatListener + WM_CLIPBOARDUPDATE
+// 通知不可用时（没有 XFixes、没有图形会话等），调用方应回退到轮询。
+//
+// ChangeCounter 提供另一种视角："剪贴板被写过几次"，用来判断一次模拟复制有没有生效。
+//
+// 密码管理器复制密码时会在剪贴板上附带一个"不要记录"的标记，`clipboard_marked_sensitive`
+// 和 `ClipboardWatcher::marked_sensitive` 负责检查它。
+
+use crate::{capture::StopToken, clipboard_content::ClipboardContent};
+use std::{
+    fmt, thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(windows)]
+mod win32;
+
+#[cfg(target_os = "linux")]
+pub use wayland::{WaylandSelection, WaylandSelectionWatcher};
+#[cfg(target_os = "linux")]
+pub use x11::X11SelectionWatcher;
+#[cfg(target_os = "linux")]
+pub(crate) use x11::active_window;
+#[cfg(windows)]
+pub use win32::ClipboardFormatListener;
+#[cfg(windows)]
+pub(crate) use win32::clipboard_rtf;
+
+/// KeePassXC 等密码管理器在 Linux 上标记敏感内容用的 MIME 类型（X11 上是同名的 target）
+pub const PASSWORD_MANAGER_HINT: &str = "x-kde-passwordManagerHint";
+
+/// 一次等待的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitOutcome {
+    /// 剪贴板内容发生了变化
+    Changed,
+    /// 超时前没有变化
+    Timeout,
+    /// 收到了停止信号
+    Stopped,
+}
+
+/// 监听器初始化或等待过程中出现的错误
+#[derive(Debug)]
+pub struct WatchError(pub String);
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for WatchError {}
+
+/// 剪贴板变化通知的来源
+pub trait ClipboardWatcher {
+    /// 后端名称，用于状态输出
+    fn name(&self) -> &'static str;
+
+    /// 阻塞等待下一次变化，最多等待 `timeout`。
+    /// 在等待之前已经积压的多次通知会被合并成一次 `Changed`。
+    fn wait_for_change(&mut self, stop: &StopToken, timeout: Duration) -> Result<WaitOutcome, WatchError>;
+
+    /// 取走最近一次变化时后端顺带读到的内容。
+    ///
+    /// 返回 `None` 表示后端只负责通知，调用方需要自己读取剪贴板；
+    /// 返回 `Some(None)` 表示后端负责读取，但这次变化没有可识别的内容（比如 selection 被清空）。
+    /// Wayland 下后台程序无法用普通方式读取剪贴板，所以由后端在通知时一并读取。
+    fn take_content(&mut self) -> Option<Option<ClipboardContent>> {
+        None
+    }
+
+    /// 最近一次变化的内容是否被密码管理器标记为不应记录。
+    ///
+    /// 默认直接检查系统剪贴板（见 `clipboard_marked_sensitive`），监听其他 selection 的后端需要自己实现。
+    /// 标记为敏感时，后端不应再读取这次的内容。
+    fn marked_sensitive(&mut self) -> bool {
+        clipboard_marked_sensitive()
+    }
+}
+
+/// 剪贴板当前的内容是否被密码管理器标记为不应记录：
+/// - Windows：有 `ExcludeClipboardContentFromMonitorProcessing` 或 `Clipboard Viewer Ignore` 格式，
+///   或者 `CanIncludeInClipboardHistory` 为 0
+/// - Linux：CLIPBOARD 提供了 `x-kde-passwordManagerHint`
+///
+/// 没有可用的剪贴板（比如连不上 X 服务器）或者查询失败时返回 false。
+pub fn clipboard_marked_sensitive() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        // 轮询时每次复制都要检查，复用同一个 X 连接；查询出错时断开，下次重新连接
+        static WATCHER: std::sync::Mutex<Option<X11SelectionWatcher>> = std::sync::Mutex::new(None);
+        let mut watcher = WATCHER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if watcher.is_none() {
+            *watcher = X11SelectionWatcher::clipboard().ok();
+        }
+        match watcher.as_mut().map(X11SelectionWatcher::offers_password_manager_hint) {
+            Some(Ok(marked)) => marked,
+            Some(Err(e)) => {
+                eprintln!("[警告] {}，无法确认内容是否敏感，照常读取。", e);
+                *watcher = None;
+                false
+            }
+            None => false,
+        }
+    }
+    #[cfg(windows)]
+    {
+        win32::clipboard_marked_sensitive()
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        false
+    }
+}
+
+// 当前是否运行在 Wayland 会话中
+#[cfg(target_os = "linux")]
+fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// 为当前平台打开剪贴板变化监听器；返回错误时调用方应回退到轮询。
+///
+/// Windows 上监听器绑定在创建它的线程上，必须在同一个线程里调用 `wait_for_change`。
+pub fn open_clipboard_watcher() -> Result<Box<dyn ClipboardWatcher>, WatchError> {
+    #[cfg(target_os = "linux")]
+    {
+        // Wayland 会话里优先使用 data-control；合成器不支持时再试 XWayland 上的 XFixes
+        if is_wayland_session() {
+            match WaylandSelectionWatcher::clipboard() {
+                Ok(watcher) => return Ok(Box::new(watcher)),
+                Err(e) => eprintln!("[状态] Wayland 剪贴板监听不可用（{}），尝试 X11。", e),
+            }
+        }
+        X11SelectionWatcher::clipboard().map(|w| Box::new(w) as Box<dyn ClipboardWatcher>)
+    }
+    #[cfg(windows)]
+    {
+        ClipboardFormatListener::new().map(|w| Box::new(w) as Box<dyn ClipboardWatcher>)
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        Err(WatchError("当前平台没有剪贴板变化通知".to_string()))
+    }
+}
+
+/// 打开 PRIMARY selection（选中即复制）的变化监听器，规则与 `open_clipboard_watcher` 相同
+#[cfg(target_os = "linux")]
+pub fn open_primary_watcher() -> Result<Box<dyn ClipboardWatcher>, WatchError> {
+    if is_wayland_session() {
+        match WaylandSelectionWatcher::primary() {
+            Ok(watcher) => return Ok(Box::new(watcher)),
+            Err(e) => eprintln!("[状态] Wayland PRIMARY selection 监听不可用（{}），尝试 X11。", e),
+        }
+    }
+    X11SelectionWatcher::new(b"PRIMARY").map(|w| Box::new(w) as Box<dyn ClipboardWatcher>)
+}
+
+/// 等待剪贴板变化的策略。
+///
+/// 从开始计时起先等 `initial_delay`，之后检查间隔从 `initial_interval` 开始每次翻倍，
+/// 最多到 `max_interval`；超过 `max_latency` 还没有变化就放弃。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitStrategy {
+    pub initial_delay: Duration,
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub max_latency: Duration,
+    /// 变化之后要保持多久不再变化，才认为写入已经完成
+    pub settle: Duration,
+}
+
+impl Default for WaitStrategy {
+    fn default() -> Self {
+        WaitStrategy {
+            initial_delay: Duration::ZERO,
+            initial_interval: Duration::from_millis(5),
+            max_interval: Duration::from_millis(50),
+            max_latency: Duration::from_millis(500),
+            settle: Duration::from_millis(30),
+        }
+    }
+}
+
+/// 计数的一次变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterChange {
+    /// 稳定之后的计数
+    pub value: u64,
+    /// 从开始计时到第一次发现变化用了多久
+    pub latency: Duration,
+}
+
+/// 剪贴板变化计数器。
+///
+/// 每次有程序写入剪贴板，计数都会改变——即使写入的内容和原来完全一样。
+/// Windows 上是 `GetClipboardSequenceNumber`，X11 上是 CLIPBOARD selection 的时间戳。
+/// 计数只能比较是否相等，不保证递增。
+pub struct ChangeCounter {
+    #[cfg(target_os = "linux")]
+    watcher: X11SelectionWatcher,
+}
+impl ChangeCounter {
+    pub fn open() -> Result<ChangeCounter, WatchError> {
+        #[cfg(target_os = "linux")]
+        {
+            Ok(ChangeCounter {
+                watcher: X11SelectionWatcher::clipboard()?,
+            })
+        }
+        #[cfg(windows)]
+        {
+            use windows::Win32::System::DataExchange::GetClipboardSequenceNumber;
+
+            // 没有访问窗口站的权限时系统返回 0
+            if unsafe { GetClipboardSequenceNumber() } == 0 {
+                return Err(WatchError("无法读取剪贴板序列号".to_string()));
+            }
+            Ok(ChangeCounter {})
+        }
+        #[cfg(not(any(target_os = "linux", windows)))]
+        {
+            Err(WatchError("当前平台没有剪贴板变化计数".to_string()))
+        }
+    }
+
+    /// 当前的计数
+    pub fn current(&mut self) -> Result<u64, WatchError> {
+        #[cfg(target_os = "linux")]
+        {
+            // 启动后还没有任何变化时没有时间戳，记为 0
+            Ok(self.watcher.poll_timestamp()?.map_or(0, u64::from))
+        }
+        #[cfg(windows)]
+        {
+            use windows::Win32::System::DataExchange::GetClipboardSequenceNumber;
+
+            Ok(u64::from(unsafe { GetClipboardSequenceNumber() }))
+        }
+        #[cfg(not(any(target_os = "linux", windows)))]
+        {
+            Err(WatchError("当前平台没有剪贴板变化计数".to_string()))
+        }
+    }
+
+    /// 等待计数从 `since` 变成别的值，按 `strategy` 退避检查，`started` 是开始计时的时刻。
+    ///
+    /// 程序写入剪贴板时往往会连续写好几种格式，每写一种计数都会变，
+    /// 所以计数变化之后还要等它稳定 `settle` 这么久才返回，这时内容才是完整的。
+    /// 超时前计数一直没变时返回 `None`。
+    pub fn wait_for_change(
+        &mut self,
+        since: u64,
+        started: Instant,
+        strategy: &WaitStrategy,
+    ) -> Result<Option<CounterChange>, WatchError> {
+        let first_check = started + strategy.initial_delay;
+        let now = Instant::now();
+        if first_check > now {
+            thread::sleep(first_check - now);
+        }
+
+        let mut interval = strategy.initial_interval;
+        let mut last = since;
+        let mut first_change: Option<Instant> = None;
+        let mut changed_at: Option<Instant> = None;
+        loop {
+            let current = self.current()?;
+            let now = Instant::now();
+            if current != last {
+                last = current;
+                first_change.get_or_insert(now);
+                changed_at = Some(now);
+                // 正在写入，缩短间隔尽快确认写完
+                interval = strategy.initial_interval;
+            }
+            let change = first_change.map(|at| CounterChange {
+                value: last,
+                latency: at.duration_since(started),
+            });
+            match changed_at {
+                Some(at) if now.duration_since(at) >= strategy.settle => return Ok(change),
+                // 已经变过了，即使超时也要把变化报告出去
+                _ if now.duration_since(started) >= strategy.max_latency => return Ok(change),
+                _ => {}
+            }
+            thread::sleep(interval);
+            interval = (interval * 2).min(strategy.max_interval);
+        }
+    }
+}
@@ -0,0 +1,498 @@
+// Wayland 后端：通过 data-control 协议监听剪贴板和 PRIMARY selection。
+// Wayland 下普通客户端只有在获得焦点时才能读到剪贴板，后台程序必须使用
+// ext-data-control-v1（较新的合成器）或 wlr-data-control-unstable-v1（Sway 等 wlroots 系）。
+// 每次 selection 变化，合成器都会先发来一个新的 data offer 及其 MIME 类型，再发 selection 事件；
+// offer 很快会被下一次复制替换，所以在收到通知时就立即把内容读出来。
+
+use super::{ClipboardWatcher, WaitOutcome, WatchError, PASSWORD_MANAGER_HINT};
+use crate::{
+    capture::StopToken,
+    clipboard_content::{
+        parse_uri_list, png_dimensions, ClipboardContent, MIME_HTML, MIME_PNG, MIME_RTF, MIME_URI_LIST,
+    },
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+    time::{Duration, Instant},
+};
+use wayland_client::{
+    backend::ObjectId,
+    event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_registry::WlRegistry, wl_seat::WlSeat},
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1::{self, ExtDataControlDeviceV1},
+    ext_data_control_manager_v1::ExtDataControlManagerV1,
+    ext_data_control_offer_v1::{self, ExtDataControlOfferV1},
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+};
+
+// 每次阻塞等待的最长时间，保证停止信号能在这个时间内被发现
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+// 读取 offer 内容的最长时间（每种格式分别计算），防止提供方不写数据时卡住
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// 按优先级排列的文本 MIME 类型
+const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+    "TEXT",
+];
+
+// RTF 的 MIME 类型，LibreOffice 用 text/rtf，也有程序用 application/rtf
+const RTF_MIME_TYPES: &[&str] = &[MIME_RTF, "application/rtf"];
+
+/// 要监听哪个 selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaylandSelection {
+    Clipboard,
+    Primary,
+}
+
+// 两种协议的 offer 接口完全一致，这里统一包装一下
+#[derive(Clone)]
+enum Offer {
+    Ext(ExtDataControlOfferV1),
+    Wlr(ZwlrDataControlOfferV1),
+}
+
+impl Offer {
+    fn id(&self) -> ObjectId {
+        match self {
+            Offer::Ext(offer) => offer.id(),
+            Offer::Wlr(offer) => offer.id(),
+        }
+    }
+
+    fn receive(&self, mime_type: &str, fd: BorrowedFd<'_>) {
+        match self {
+            Offer::Ext(offer) => offer.receive(mime_type.to_string(), fd),
+            Offer::Wlr(offer) => offer.receive(mime_type.to_string(), fd),
+        }
+    }
+
+    fn destroy(&self) {
+        match self {
+            Offer::Ext(offer) => offer.destroy(),
+            Offer::Wlr(offer) => offer.destroy(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    target: Option<WaylandSelection>,
+    // 尚未成为 selection 的 offer 及其 MIME 类型
+    mime_types: HashMap<ObjectId, Vec<String>>,
+    // 最近一次 selection 变化对应的 offer；None 表示 selection 被清空
+    current: Option<(Offer, Vec<String>)>,
+    changed: bool,
+    finished: bool,
+}
+
+impl State {
+    fn on_selection(&mut self, selection: WaylandSelection, offer: Option<Offer>) {
+        if self.target != Some(selection) {
+            // 不关心的那个 selection 的 offer 直接释放
+            if let Some(offer) = offer {
+                self.mime_types.remove(&offer.id());
+                offer.destroy();
+            }
+            return;
+        }
+        if let Some((old, _)) = self.current.take() {
+            old.destroy();
+        }
+        self.current = offer.map(|offer| {
+            let mime_types = self.mime_types.remove(&offer.id()).unwrap_or_default();
+            (offer, mime_types)
+        });
+        self.changed = true;
+    }
+}
+
+/// 基于 data-control 协议的 Wayland selection 监听器
+pub struct WaylandSelectionWatcher {
+    conn: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    protocol: &'static str,
+    // 最近一次变化读到的内容和 MIME 类型，等待调用方取走
+    content: Option<ClipboardContent>,
+    mime_types: Vec<String>,
+    // 最近一次变化的内容带有密码管理器的标记，这时不读取内容
+    marked: bool,
+}
+
+impl WaylandSelectionWatcher {
+    /// 监听剪贴板
+    pub fn clipboard() -> Result<WaylandSelectionWatcher, WatchError> {
+        WaylandSelectionWatcher::new(WaylandSelection::Clipboard)
+    }
+
+    /// 监听 PRIMARY selection（选中即复制）
+    pub fn primary() -> Result<WaylandSelectionWatcher, WatchError> {
+        WaylandSelectionWatcher::new(WaylandSelection::Primary)
+    }
+
+    pub fn new(selection: WaylandSelection) -> Result<WaylandSelectionWatcher, WatchError> {
+        let conn = Connection::connect_to_env()
+            .map_err(|e| WatchError(format!("无法连接 Wayland: {}", e)))?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)
+            .map_err(|e| WatchError(format!("读取 Wayland 全局对象失败: {}", e)))?;
+        let qh = queue.handle();
+
+        let seat: WlSeat = globals
+            .bind(&qh, 1..=1, ())
+            .map_err(|e| WatchError(format!("没有可用的 wl_seat: {}", e)))?;
+
+        // 优先使用标准化的 ext-data-control，旧合成器上退回 wlr-data-control。
+        // PRIMARY selection 在 wlr 协议里从版本 2 开始才有
+        let protocol = if let Ok(manager) = globals.bind::<ExtDataControlManagerV1, _, _>(&qh, 1..=1, ()) {
+            manager.get_data_device(&seat, &qh, ());
+            "ext-data-control-v1"
+        } else {
+            let min_version = if selection == WaylandSelection::Primary { 2 } else { 1 };
+            let manager = globals
+                .bind::<ZwlrDataControlManagerV1, _, _>(&qh, min_version..=2, ())
+                .map_err(|_| WatchError("合成器不支持 data-control 协议".to_string()))?;
+            manager.get_data_device(&seat, &qh, ());
+            "wlr-data-control-unstable-v1"
+        };
+
+        let mut state = State {
+            target: Some(selection),
+            ..State::default()
+        };
+        // 第一次往返会收到当前已有的 selection，它不算一次新的变化
+        queue
+            .roundtrip(&mut state)
+            .map_err(|e| WatchError(format!("Wayland 通信失败: {}", e)))?;
+        state.changed = false;
+
+        Ok(WaylandSelectionWatcher {
+            conn,
+            queue,
+            state,
+            protocol,
+            content: None,
+            mime_types: Vec::new(),
+            marked: false,
+        })
+    }
+
+    /// 最近一次变化时 offer 提供的全部 MIME 类型
+    pub fn mime_types(&self) -> &[String] {
+        &self.mime_types
+    }
+
+    // 读取并分发已到达的事件，最多等待 timeout
+    fn dispatch(&mut self, timeout: Duration) -> Result<(), WatchError> {
+        let err = |e: &dyn std::fmt::Display| WatchError(format!("Wayland 通信失败: {}", e));
+        self.queue.dispatch_pending(&mut self.state).map_err(|e| err(&e))?;
+        if self.state.changed {
+            return Ok(());
+        }
+        self.queue.flush().map_err(|e| err(&e))?;
+        if let Some(guard) = self.queue.prepare_read() {
+            if wait_readable(guard.connection_fd(), timeout)? {
+                guard.read().map_err(|e| err(&e))?;
+            }
+        }
+        self.queue.dispatch_pending(&mut self.state).map_err(|e| err(&e))?;
+        if self.state.finished {
+            return Err(WatchError("data-control 设备已被合成器销毁".to_string()));
+        }
+        Ok(())
+    }
+
+    // 从当前 offer 读出内容。剪贴板按文件列表、HTML、RTF、纯文本、图片的优先级读取
+    // （与 ClipboardContent::read 一致），PRIMARY selection 只读纯文本
+    fn receive_content(&mut self) -> Result<Option<ClipboardContent>, WatchError> {
+        let Some((offer, mime_types)) = self.state.current.clone() else {
+            return Ok(None);
+        };
+        let has = |wanted: &str| mime_types.iter().any(|m| m == wanted);
+        let text_mime_type = TEXT_MIME_TYPES.iter().copied().find(|wanted| has(wanted));
+        let mut text = match text_mime_type {
+            Some(mime_type) => Some(String::from_utf8_lossy(&self.receive(&offer, mime_type)?).into_owned()),
+            None => None,
+        };
+        if self.state.target == Some(WaylandSelection::Primary) {
+            return Ok(text.map(ClipboardContent::Text));
+        }
+
+        if has(MIME_URI_LIST) {
+            let files = parse_uri_list(&String::from_utf8_lossy(&self.receive(&offer, MIME_URI_LIST)?));
+            if !files.is_empty() {
+                return Ok(Some(ClipboardContent::Files(files)));
+            }
+        }
+        text = text.filter(|text| !text.is_empty());
+        if has(MIME_HTML) {
+            let html = String::from_utf8_lossy(&self.receive(&offer, MIME_HTML)?).into_owned();
+            if !html.trim().is_empty() {
+                return Ok(Some(ClipboardContent::Html { html, text }));
+            }
+        }
+        if let Some(mime_type) = RTF_MIME_TYPES.iter().copied().find(|wanted| has(wanted)) {
+            let rtf = String::from_utf8_lossy(&self.receive(&offer, mime_type)?).into_owned();
+            if !rtf.trim().is_empty() {
+                return Ok(Some(ClipboardContent::Rtf { rtf, text }));
+            }
+        }
+        if let Some(text) = text {
+            return Ok(Some(ClipboardContent::Text(text)));
+        }
+        if has(MIME_PNG) {
+            // 直接拿到 PNG，不需要重新编码
+            let png = self.receive(&offer, MIME_PNG)?;
+            if let Some((width, height)) = png_dimensions(&png) {
+                return Ok(Some(ClipboardContent::Image { width, height, png }));
+            }
+        }
+        Ok(None)
+    }
+
+    // 请求提供方以指定的 MIME 类型写出内容，并读取全部数据
+    fn receive(&mut self, offer: &Offer, mime_type: &str) -> Result<Vec<u8>, WatchError> {
+        let (read_end, write_end) = pipe()?;
+        offer.receive(mime_type, write_end.as_fd());
+        self.conn
+            .flush()
+            .map_err(|e| WatchError(format!("Wayland 通信失败: {}", e)))?;
+        // 关闭我们这边的写端，提供方写完并关闭后读端才会读到 EOF
+        drop(write_end);
+
+        let mut file = File::from(read_end);
+        let mut data = Vec::new();
+        let deadline = Instant::now() + RECEIVE_TIMEOUT;
+        let mut buffer = [0u8; 8192];
+        loop {
+            let now = Instant::now();
+            if now >= deadline || !wait_readable(file.as_fd(), deadline - now)? {
+                return Err(WatchError("读取 selection 内容超时".to_string()));
+            }
+            match file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => data.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(WatchError(format!("读取 selection 内容失败: {}", e))),
+            }
+        }
+        Ok(data)
+    }
+}
+
+impl ClipboardWatcher for WaylandSelectionWatcher {
+    fn name(&self) -> &'static str {
+        self.protocol
+    }
+
+    fn wait_for_change(&mut self, stop: &StopToken, timeout: Duration) -> Result<WaitOutcome, WatchError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if stop.is_stopped() {
+                return Ok(WaitOutcome::Stopped);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(WaitOutcome::Timeout);
+            }
+            self.dispatch((deadline - now).min(STOP_CHECK_INTERVAL))?;
+            if self.state.changed {
+                self.state.changed = false;
+                self.mime_types = self
+                    .state
+                    .current
+                    .as_ref()
+                    .map(|(_, mime_types)| mime_types.clone())
+                    .unwrap_or_default();
+                self.marked = self.mime_types.iter().any(|m| m == PASSWORD_MANAGER_HINT);
+                // 读取失败（提供方已退出等）时仍然报告变化，只是没有内容
+                self.content = if self.marked {
+                    None
+                } else {
+                    self.receive_content().unwrap_or_else(|e| {
+                        eprintln!("[警告] {}", e);
+                        None
+                    })
+                };
+                return Ok(WaitOutcome::Changed);
+            }
+        }
+    }
+
+    fn take_content(&mut self) -> Option<Option<ClipboardContent>> {
+        Some(self.content.take())
+    }
+
+    fn marked_sensitive(&mut self) -> bool {
+        self.marked
+    }
+}
+
+// 创建一对带 CLOEXEC 标志的管道
+fn pipe() -> Result<(OwnedFd, OwnedFd), WatchError> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(WatchError(format!(
+            "创建管道失败: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+    unsafe { Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))) }
+}
+
+// 等待 fd 可读，返回是否可读
+fn wait_readable(fd: BorrowedFd<'_>, timeout: Duration) -> Result<bool, WatchError> {
+    let mut pollfd = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+    let result = unsafe { libc::poll(&mut pollfd, 1, millis) };
+    if result < 0 {
+        let err = std::io::Error::last_os_error();
+        if err.kind() == std::io::ErrorKind::Interrupted {
+            return Ok(false);
+        }
+        return Err(WatchError(format!("等待 Wayland 事件失败: {}", err)));
+    }
+    Ok(result > 0)
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut State,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(_: &mut State, _: &WlSeat, _: <WlSeat as Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<State>) {}
+}
+
+impl Dispatch<ExtDataControlManagerV1, ()> for State {
+    fn event(
+        _: &mut State,
+        _: &ExtDataControlManagerV1,
+        _: <ExtDataControlManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for State {
+    fn event(
+        _: &mut State,
+        _: &ZwlrDataControlManagerV1,
+        _: <ZwlrDataControlManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut State,
+        _: &ExtDataControlDeviceV1,
+        event: ext_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+        use ext_data_control_device_v1::Event;
+        match event {
+            Event::DataOffer { id } => {
+                state.mime_types.insert(id.id(), Vec::new());
+            }
+            Event::Selection { id } => state.on_selection(WaylandSelection::Clipboard, id.map(Offer::Ext)),
+            Event::PrimarySelection { id } => state.on_selection(WaylandSelection::Primary, id.map(Offer::Ext)),
+            Event::Finished => state.finished = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ExtDataControlDeviceV1, [
+        ext_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ExtDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut State,
+        _: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+        use zwlr_data_control_device_v1::Event;
+        match event {
+            Event::DataOffer { id } => {
+                state.mime_types.insert(id.id(), Vec::new());
+            }
+            Event::Selection { id } => state.on_selection(WaylandSelection::Clipboard, id.map(Offer::Wlr)),
+            Event::PrimarySelection { id } => state.on_selection(WaylandSelection::Primary, id.map(Offer::Wlr)),
+            Event::Finished => state.finished = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut State,
+        offer: &ExtDataControlOfferV1,
+        event: ext_data_control_offer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+        if let ext_data_control_offer_v1::Event::Offer { mime_type } = event {
+            state.mime_types.entry(offer.id()).or_default().push(mime_type);
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut State,
+        offer: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            state.mime_types.entry(offer.id()).or_default().push(mime_type);
+        }
+    }
+}
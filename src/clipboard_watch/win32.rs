@@ -0,0 +1,160 @@
+// Windows 后端：创建一个仅用于接收消息的窗口并注册剪贴板格式监听器。
+// 每次剪贴板内容变化，系统都会向这个窗口投递 WM_CLIPBOARDUPDATE。
+
+use super::{ClipboardWatcher, WaitOutcome, WatchError};
+use crate::{
+    capture::StopToken,
+    clipboard_snapshot::{read_global, OpenGuard},
+};
+use std::time::{Duration, Instant};
+use windows::{
+    core::w,
+    Win32::{
+        Foundation::HWND,
+        System::DataExchange::{
+            AddClipboardFormatListener, GetClipboardData, IsClipboardFormatAvailable, RegisterClipboardFormatW,
+            RemoveClipboardFormatListener,
+        },
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DestroyWindow, DispatchMessageW, MsgWaitForMultipleObjects, PeekMessageW,
+            TranslateMessage, HWND_MESSAGE, MSG, PM_REMOVE, QS_ALLINPUT, WINDOW_EX_STYLE, WINDOW_STYLE,
+            WM_CLIPBOARDUPDATE,
+        },
+    },
+};
+
+// 每次阻塞等待的最长时间，保证停止信号能在这个时间内被发现
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 基于 AddClipboardFormatListener 的剪贴板变化监听器。
+/// 消息窗口属于创建它的线程，只能在该线程里等待。
+pub struct ClipboardFormatListener {
+    hwnd: HWND,
+}
+
+impl ClipboardFormatListener {
+    pub fn new() -> Result<ClipboardFormatListener, WatchError> {
+        unsafe {
+            // 使用系统自带的 STATIC 窗口类，省去注册窗口类；父窗口为 HWND_MESSAGE 表示仅消息窗口
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                w!("STATIC"),
+                w!("text_listener clipboard listener"),
+                WINDOW_STYLE(0),
+                0,
+                0,
+                0,
+                0,
+                HWND_MESSAGE,
+                None,
+                None,
+                None,
+            );
+            if hwnd.0 == 0 {
+                return Err(WatchError(format!(
+                    "创建消息窗口失败: {:?}",
+                    windows::core::Error::from_win32()
+                )));
+            }
+            if let Err(e) = AddClipboardFormatListener(hwnd) {
+                let _ = DestroyWindow(hwnd);
+                return Err(WatchError(format!("注册剪贴板监听器失败: {:?}", e)));
+            }
+            Ok(ClipboardFormatListener { hwnd })
+        }
+    }
+
+    // 取出线程队列里所有已到达的消息，返回其中是否有 WM_CLIPBOARDUPDATE。
+    // 其他消息照常分发，避免它们一直留在队列里
+    unsafe fn drain_updates(&self) -> bool {
+        let mut changed = false;
+        let mut msg = MSG::default();
+        while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+            if msg.hwnd == self.hwnd && msg.message == WM_CLIPBOARDUPDATE {
+                changed = true;
+            } else {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        changed
+    }
+}
+
+impl ClipboardWatcher for ClipboardFormatListener {
+    fn name(&self) -> &'static str {
+        "AddClipboardFormatListener"
+    }
+
+    fn wait_for_change(&mut self, stop: &StopToken, timeout: Duration) -> Result<WaitOutcome, WatchError> {
+        let deadline = Instant::now() + timeout;
+        unsafe {
+            loop {
+                if stop.is_stopped() {
+                    return Ok(WaitOutcome::Stopped);
+                }
+                if self.drain_updates() {
+                    return Ok(WaitOutcome::Changed);
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(WaitOutcome::Timeout);
+                }
+                let wait = (deadline - now).min(STOP_CHECK_INTERVAL);
+                MsgWaitForMultipleObjects(None, false, wait.as_millis() as u32, QS_ALLINPUT);
+            }
+        }
+    }
+}
+
+impl Drop for ClipboardFormatListener {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = RemoveClipboardFormatListener(self.hwnd);
+            let _ = DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+/// 剪贴板上是否有密码管理器留下的"不要记录"标记
+pub fn clipboard_marked_sensitive() -> bool {
+    unsafe {
+        // 只要有这两种格式就表示不要记录，不用看内容
+        for name in [w!("ExcludeClipboardContentFromMonitorProcessing"), w!("Clipboard Viewer Ignore")] {
+            let format = RegisterClipboardFormatW(name);
+            if format != 0 && IsClipboardFormatAvailable(format).is_ok() {
+                return true;
+            }
+        }
+
+        // CanIncludeInClipboardHistory 是一个 DWORD，为 0 时表示不要进入剪贴板历史
+        let format = RegisterClipboardFormatW(w!("CanIncludeInClipboardHistory"));
+        if format == 0 || IsClipboardFormatAvailable(format).is_err() {
+            return false;
+        }
+        // 读不到值时按敏感处理，宁可少记一条
+        let Ok(_guard) = OpenGuard::open() else {
+            return true;
+        };
+        match GetClipboardData(format).ok().and_then(|handle| read_global(handle)) {
+            Some(data) if data.len() >= 4 => u32::from_le_bytes([data[0], data[1], data[2], data[3]]) == 0,
+            _ => true,
+        }
+    }
+}
+
+/// 剪贴板上的 RTF（`Rich Text Format` 格式），没有或读取失败时返回 None。
+/// arboard 不支持 RTF，所以直接用 Win32 API 读取
+pub(crate) fn clipboard_rtf() -> Option<String> {
+    unsafe {
+        let format = RegisterClipboardFormatW(w!("Rich Text Format"));
+        if format == 0 || IsClipboardFormatAvailable(format).is_err() {
+            return None;
+        }
+        let _guard = OpenGuard::open().ok()?;
+        let data = GetClipboardData(format).ok().and_then(|handle| read_global(handle))?;
+        // 全局内存块可能比内容长，末尾用 0 填充
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        Some(String::from_utf8_lossy(&data[..end]).into_owned())
+    }
+}
@@ -0,0 +1,298 @@
+// X11 后端：通过 XFixes 订阅 selection owner 的变化。
+// 每次有程序复制内容，它都会成为 CLIPBOARD 的新 owner，X server 就会发来 SelectionNotify。
+
+use super::{ClipboardWatcher, WaitOutcome, WatchError, PASSWORD_MANAGER_HINT};
+use crate::capture::{process_image, StopToken, WindowInfo};
+use std::{
+    os::unix::io::AsRawFd,
+    time::{Duration, Instant},
+};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xfixes::{ConnectionExt as _, SelectionEventMask},
+        xproto::{Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, Timestamp, Window, WindowClass},
+        Event,
+    },
+    rust_connection::RustConnection,
+    COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE,
+};
+
+// 每次阻塞等待的最长时间，保证停止信号能在这个时间内被发现
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+// 等待 selection 的 owner 回复 TARGETS 的最长时间
+const TARGETS_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// 监听某个 X11 selection（CLIPBOARD、PRIMARY 等）的 owner 变化
+pub struct X11SelectionWatcher {
+    conn: RustConnection,
+    window: Window,
+    selection: Atom,
+    // 最近一次变化时 selection 的时间戳
+    last_timestamp: Option<Timestamp>,
+    // 等待 TARGETS 回复时顺带收到的变化，留给下一次 wait_for_change 报告
+    pending_change: bool,
+}
+
+impl X11SelectionWatcher {
+    /// 监听 CLIPBOARD（Ctrl+C 复制的内容）
+    pub fn clipboard() -> Result<X11SelectionWatcher, WatchError> {
+        X11SelectionWatcher::new(b"CLIPBOARD")
+    }
+
+    /// 监听指定名称的 selection
+    pub fn new(selection_name: &[u8]) -> Result<X11SelectionWatcher, WatchError> {
+        let (conn, screen_num) =
+            x11rb::connect(None).map_err(|e| WatchError(format!("无法连接 X11: {}", e)))?;
+        let err = |e: &dyn std::fmt::Display| WatchError(format!("XFixes 初始化失败: {}", e));
+
+        // 必须先协商版本，否则 XFixes 请求会被拒绝
+        conn.xfixes_query_version(5, 0)
+            .map_err(|e| err(&e))?
+            .reply()
+            .map_err(|e| err(&e))?;
+
+        let selection = conn
+            .intern_atom(false, selection_name)
+            .map_err(|e| err(&e))?
+            .reply()
+            .map_err(|e| err(&e))?
+            .atom;
+
+        // 事件需要投递到一个窗口上，创建一个不映射的 1x1 窗口
+        let root = conn.setup().roots[screen_num].root;
+        let window = conn.generate_id().map_err(|e| err(&e))?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )
+        .map_err(|e| err(&e))?;
+
+        conn.xfixes_select_selection_input(
+            window,
+            selection,
+            SelectionEventMask::SET_SELECTION_OWNER
+                | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+        )
+        .map_err(|e| err(&e))?;
+        conn.flush().map_err(|e| err(&e))?;
+
+        Ok(X11SelectionWatcher {
+            conn,
+            window,
+            selection,
+            last_timestamp: None,
+            pending_change: false,
+        })
+    }
+
+    /// 最近一次变化时 selection 的时间戳（X server 时间，毫秒）
+    pub fn last_timestamp(&self) -> Option<Timestamp> {
+        self.last_timestamp
+    }
+
+    /// 不阻塞地处理已到达的事件，返回最新的 selection 时间戳
+    pub fn poll_timestamp(&mut self) -> Result<Option<Timestamp>, WatchError> {
+        self.drain_events()?;
+        Ok(self.last_timestamp)
+    }
+
+    /// 询问 selection 当前的 owner 是否提供某种 target（格式）。没有 owner 时返回 false
+    pub fn offers_target(&mut self, target: &str) -> Result<bool, WatchError> {
+        let err = |e: &dyn std::fmt::Display| WatchError(format!("读取 selection 格式失败: {}", e));
+        let intern = |name: &[u8]| -> Result<Atom, WatchError> {
+            Ok(self
+                .conn
+                .intern_atom(false, name)
+                .map_err(|e| err(&e))?
+                .reply()
+                .map_err(|e| err(&e))?
+                .atom)
+        };
+        let targets = intern(b"TARGETS")?;
+        let wanted = intern(target.as_bytes())?;
+        let property = intern(b"TEXT_LISTENER_TARGETS")?;
+
+        self.conn
+            .convert_selection(self.window, self.selection, targets, property, CURRENT_TIME)
+            .map_err(|e| err(&e))?;
+        self.conn.flush().map_err(|e| err(&e))?;
+
+        let deadline = Instant::now() + TARGETS_TIMEOUT;
+        let notify = loop {
+            match self.conn.poll_for_event().map_err(|e| err(&e))? {
+                Some(Event::SelectionNotify(notify)) if notify.requestor == self.window => break notify,
+                Some(Event::XfixesSelectionNotify(notify)) => {
+                    self.last_timestamp = Some(notify.selection_timestamp);
+                    self.pending_change = true;
+                }
+                Some(_) => {}
+                None => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(WatchError("selection 的 owner 没有回复".to_string()));
+                    }
+                    self.wait_readable((deadline - now).min(STOP_CHECK_INTERVAL))?;
+                }
+            }
+        };
+        // owner 拒绝转换或者没有 owner
+        if notify.property == NONE {
+            return Ok(false);
+        }
+        let reply = self
+            .conn
+            .get_property(true, self.window, property, AtomEnum::ATOM, 0, 1024)
+            .map_err(|e| err(&e))?
+            .reply()
+            .map_err(|e| err(&e))?;
+        Ok(reply.value32().is_some_and(|mut atoms| atoms.any(|atom| atom == wanted)))
+    }
+
+    /// owner 是否给内容加了密码管理器的标记。owner 可能只是一时没有回复，失败时再问一次
+    pub fn offers_password_manager_hint(&mut self) -> Result<bool, WatchError> {
+        self.offers_target(PASSWORD_MANAGER_HINT)
+            .or_else(|_| self.offers_target(PASSWORD_MANAGER_HINT))
+    }
+
+    // 取出所有已到达的事件，返回其中是否有 selection 变化
+    fn drain_events(&mut self) -> Result<bool, WatchError> {
+        let mut changed = std::mem::take(&mut self.pending_change);
+        while let Some(event) = self
+            .conn
+            .poll_for_event()
+            .map_err(|e| WatchError(format!("读取 X11 事件失败: {}", e)))?
+        {
+            if let Event::XfixesSelectionNotify(notify) = event {
+                self.last_timestamp = Some(notify.selection_timestamp);
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
+    // 等待 X11 连接可读，最多等待 timeout
+    fn wait_readable(&self, timeout: Duration) -> Result<(), WatchError> {
+        let mut pollfd = libc::pollfd {
+            fd: self.conn.stream().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+        let result = unsafe { libc::poll(&mut pollfd, 1, millis) };
+        if result < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() != std::io::ErrorKind::Interrupted {
+                return Err(WatchError(format!("等待 X11 事件失败: {}", err)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ClipboardWatcher for X11SelectionWatcher {
+    fn name(&self) -> &'static str {
+        "X11 XFixes"
+    }
+
+    // 只有明确提供了标记才算敏感。owner 回复得慢不代表内容敏感，查询失败时照常读取
+    fn marked_sensitive(&mut self) -> bool {
+        self.offers_password_manager_hint().unwrap_or_else(|e| {
+            eprintln!("[警告] {}，无法确认内容是否敏感，照常读取。", e);
+            false
+        })
+    }
+
+    fn wait_for_change(&mut self, stop: &StopToken, timeout: Duration) -> Result<WaitOutcome, WatchError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if stop.is_stopped() {
+                return Ok(WaitOutcome::Stopped);
+            }
+            // 先处理已经读进缓冲区的事件，poll 只能发现还在 socket 里的数据
+            if self.drain_events()? {
+                return Ok(WaitOutcome::Changed);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(WaitOutcome::Timeout);
+            }
+            self.wait_readable((deadline - now).min(STOP_CHECK_INTERVAL))?;
+        }
+    }
+}
+
+impl Drop for X11SelectionWatcher {
+    fn drop(&mut self) {
+        let _ = self.conn.destroy_window(self.window);
+        let _ = self.conn.flush();
+    }
+}
+
+/// 读取当前活动窗口（`_NET_ACTIVE_WINDOW`）的标题、类名和所属进程。
+/// 没有 X server 或者窗口管理器不支持 EWMH 时返回 None。
+pub fn active_window() -> Option<WindowInfo> {
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots[screen].root;
+    let atom = |name: &str| -> Option<Atom> { Some(conn.intern_atom(false, name.as_bytes()).ok()?.reply().ok()?.atom) };
+
+    let active = atom("_NET_ACTIVE_WINDOW")?;
+    let window = conn
+        .get_property(false, root, active, AtomEnum::WINDOW, 0, 1)
+        .ok()?
+        .reply()
+        .ok()?
+        .value32()?
+        .next()?;
+    if window == NONE {
+        return None;
+    }
+
+    let property = |name: Atom, kind: AtomEnum| -> Option<Vec<u8>> {
+        let reply = conn.get_property(false, window, name, kind, 0, 1024).ok()?.reply().ok()?;
+        (reply.format == 8 && !reply.value.is_empty()).then_some(reply.value)
+    };
+    // 优先使用 UTF-8 的 _NET_WM_NAME，老程序只设置 WM_NAME
+    let title = atom("_NET_WM_NAME")
+        .and_then(|name| property(name, AtomEnum::ANY))
+        .or_else(|| property(AtomEnum::WM_NAME.into(), AtomEnum::ANY))
+        .map(|title| String::from_utf8_lossy(&title).into_owned())
+        .unwrap_or_else(|| "未知窗口".to_string());
+    // WM_CLASS 的内容是 "实例名\0类名\0"
+    let class = property(AtomEnum::WM_CLASS.into(), AtomEnum::STRING).and_then(|value| {
+        value
+            .split(|&b| b == 0)
+            .rfind(|part| !part.is_empty())
+            .map(|class| String::from_utf8_lossy(class).into_owned())
+    });
+    let pid = atom("_NET_WM_PID").and_then(|name| {
+        conn.get_property(false, window, name, AtomEnum::CARDINAL, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()?
+            .next()
+    });
+
+    let (process, exe_path) = pid.map_or((None, None), process_image);
+
+    Some(WindowInfo {
+        title,
+        process,
+        class,
+        pid,
+        exe_path,
+        url: None,
+    })
+}
@@ -0,0 +1,204 @@
+// --- 模拟复制的耗时统计 ---
+// 不同程序响应 Ctrl+C 的速度差别很大：记事本几毫秒就写好剪贴板，Electron 编辑器和远程桌面
+// 可能要几百毫秒。这里按进程记录每次模拟复制等了多久，下次对同一个程序调整等待策略。
+
+use crate::clipboard_watch::WaitStrategy;
+use std::{collections::HashMap, time::Duration};
+
+// 至少有这么多次成功的样本才调整策略
+const MIN_SAMPLES: u32 = 3;
+
+// 没有变化计数、只能固定等待时的默认时长
+const DEFAULT_FIXED_DELAY: Duration = Duration::from_millis(150);
+
+/// 某个程序的模拟复制耗时
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessTiming {
+    /// 剪贴板在等待时间内发生变化的次数
+    pub samples: u32,
+    /// 等到超时也没有变化的次数（多半是没有选中内容）
+    pub timeouts: u32,
+    pub fastest: Duration,
+    pub slowest: Duration,
+    /// 指数加权平均，越近的样本权重越大
+    pub average: Duration,
+}
+
+impl ProcessTiming {
+    fn new() -> ProcessTiming {
+        ProcessTiming {
+            samples: 0,
+            timeouts: 0,
+            fastest: Duration::MAX,
+            slowest: Duration::ZERO,
+            average: Duration::ZERO,
+        }
+    }
+
+    fn record(&mut self, latency: Duration) {
+        self.average = if self.samples == 0 {
+            latency
+        } else {
+            // 新样本占 1/4
+            (self.average * 3 + latency) / 4
+        };
+        self.samples += 1;
+        self.fastest = self.fastest.min(latency);
+        self.slowest = self.slowest.max(latency);
+    }
+}
+
+/// 按进程汇总的模拟复制耗时
+#[derive(Debug, Clone, Default)]
+pub struct CopyTimingStats {
+    processes: HashMap<String, ProcessTiming>,
+}
+
+impl CopyTimingStats {
+    pub fn new() -> CopyTimingStats {
+        CopyTimingStats::default()
+    }
+
+    /// 记录一次模拟复制：`latency` 为剪贴板开始变化用的时间，超时为 None
+    pub fn record(&mut self, process: &str, latency: Option<Duration>) {
+        let timing = self
+            .processes
+            .entry(process.to_string())
+            .or_insert_with(ProcessTiming::new);
+        match latency {
+            Some(latency) => timing.record(latency),
+            None => timing.timeouts += 1,
+        }
+    }
+
+    pub fn get(&self, process: &str) -> Option<&ProcessTiming> {
+        self.processes.get(process)
+    }
+
+    /// 针对某个程序调整后的等待策略。
+    ///
+    /// 这个程序从来没有比 `fastest` 更快过，所以先直接睡掉其中的大部分，省掉无用的检查；
+    /// 检查间隔从平均耗时的十分之一开始。最长等待时间保持不变，偶尔变慢的时候也不会漏掉。
+    pub fn strategy_for(&self, process: Option<&str>, base: &WaitStrategy) -> WaitStrategy {
+        let Some(timing) = self.tuned(process) else {
+            return *base;
+        };
+        WaitStrategy {
+            initial_delay: (timing.fastest * 4 / 5).min(base.max_latency),
+            initial_interval: (timing.average / 10).clamp(base.initial_interval, base.max_interval),
+            ..*base
+        }
+    }
+
+    /// 没有变化计数时的固定等待时长，不超过最长等待时间。
+    ///
+    /// 固定等待测不出耗时，只有变化计数测到的样本会被记录：计数偶尔读取失败时按之前测到的
+    /// 这个程序最慢的一次留出余量，从来没有计数可用时始终是默认时长。
+    pub fn fixed_delay_for(&self, process: Option<&str>, base: &WaitStrategy) -> Duration {
+        match self.tuned(process) {
+            Some(timing) => (timing.slowest * 6 / 5).min(base.max_latency),
+            None => DEFAULT_FIXED_DELAY.min(base.max_latency),
+        }
+    }
+
+    /// 所有程序的统计，按样本数从多到少排列
+    pub fn summary(&self) -> Vec<(&str, &ProcessTiming)> {
+        let mut entries: Vec<_> = self
+            .processes
+            .iter()
+            .map(|(process, timing)| (process.as_str(), timing))
+            .collect();
+        entries.sort_by(|a, b| b.1.samples.cmp(&a.1.samples).then(a.0.cmp(b.0)));
+        entries
+    }
+
+    fn tuned(&self, process: Option<&str>) -> Option<&ProcessTiming> {
+        self.processes
+            .get(process?)
+            .filter(|timing| timing.samples >= MIN_SAMPLES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn stats_with(process: &str, latencies: &[u64]) -> CopyTimingStats {
+        let mut stats = CopyTimingStats::new();
+        for &latency in latencies {
+            stats.record(process, Some(ms(latency)));
+        }
+        stats
+    }
+
+    #[test]
+    fn unknown_or_untuned_process_uses_base() {
+        let base = WaitStrategy::default();
+        let stats = stats_with("notepad.exe", &[10, 20]);
+        for process in [None, Some("code.exe"), Some("notepad.exe")] {
+            assert_eq!(stats.strategy_for(process, &base), base);
+            assert_eq!(stats.fixed_delay_for(process, &base), DEFAULT_FIXED_DELAY);
+        }
+        // 默认时长也不超过最长等待时间
+        let short = WaitStrategy {
+            max_latency: ms(100),
+            ..base
+        };
+        assert_eq!(stats.fixed_delay_for(None, &short), ms(100));
+    }
+
+    #[test]
+    fn timeouts_are_not_samples() {
+        let mut stats = stats_with("code.exe", &[100, 100]);
+        stats.record("code.exe", None);
+        stats.record("code.exe", None);
+        let timing = stats.get("code.exe").unwrap();
+        assert_eq!((timing.samples, timing.timeouts), (2, 2));
+        assert_eq!(stats.fixed_delay_for(Some("code.exe"), &WaitStrategy::default()), DEFAULT_FIXED_DELAY);
+    }
+
+    #[test]
+    fn strategy_follows_measured_latency() {
+        let base = WaitStrategy::default();
+        let stats = stats_with("code.exe", &[100, 200, 300]);
+        let timing = stats.get("code.exe").unwrap();
+        // 100 → (100×3 + 200) / 4 = 125 → (125×3 + 300) / 4 = 168.75
+        assert_eq!(timing.average, Duration::from_micros(168_750));
+        assert_eq!((timing.fastest, timing.slowest), (ms(100), ms(300)));
+
+        let strategy = stats.strategy_for(Some("code.exe"), &base);
+        assert_eq!(strategy.initial_delay, ms(80));
+        assert_eq!(strategy.initial_interval, Duration::from_micros(16_875));
+        assert_eq!(strategy.max_latency, base.max_latency);
+        assert_eq!(stats.fixed_delay_for(Some("code.exe"), &base), ms(360));
+    }
+
+    #[test]
+    fn tuned_values_are_clamped() {
+        let base = WaitStrategy::default();
+        let slow = stats_with("mstsc.exe", &[2000, 2000, 2000]);
+        let strategy = slow.strategy_for(Some("mstsc.exe"), &base);
+        assert_eq!(strategy.initial_delay, base.max_latency);
+        assert_eq!(strategy.initial_interval, base.max_interval);
+        assert_eq!(slow.fixed_delay_for(Some("mstsc.exe"), &base), base.max_latency);
+
+        let fast = stats_with("notepad.exe", &[1, 1, 1]);
+        let strategy = fast.strategy_for(Some("notepad.exe"), &base);
+        assert_eq!(strategy.initial_delay, Duration::from_micros(800));
+        assert_eq!(strategy.initial_interval, base.initial_interval);
+    }
+
+    #[test]
+    fn summary_sorted_by_samples_then_name() {
+        let mut stats = stats_with("b.exe", &[10]);
+        stats.record("a.exe", Some(ms(10)));
+        stats.record("c.exe", Some(ms(10)));
+        stats.record("c.exe", Some(ms(10)));
+        let order: Vec<&str> = stats.summary().into_iter().map(|(process, _)| process).collect();
+        assert_eq!(order, ["c.exe", "a.exe", "b.exe"]);
+    }
+}
@@ -0,0 +1,202 @@
+// --- 鼠标手势识别 ---
+// 方法三原来在每次左键抬起时都模拟 Ctrl+C：点一下按钮、任务栏也会触发，
+// 而快速双击选词又会被 300ms 的防抖过滤掉。
+// 这里根据按下和抬起的位置、时间，把一次操作分成单击、拖动选择、双击和三击，
+// 只有后三种才可能选中了文本。
+
+/// 一次左键操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    /// 原地单击，不会选中文本
+    Click,
+    /// 按住拖动，通常是在选择一段文本
+    Drag,
+    /// 双击，通常选中一个词
+    DoubleClick,
+    /// 三击，通常选中一行或一段
+    TripleClick,
+}
+
+impl Gesture {
+    /// 这种操作是否可能选中了文本
+    pub fn selects_text(self) -> bool {
+        !matches!(self, Gesture::Click)
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Gesture::Click => "单击",
+            Gesture::Drag => "拖动选择",
+            Gesture::DoubleClick => "双击",
+            Gesture::TripleClick => "三击",
+        }
+    }
+}
+
+/// 判断手势用的阈值，Windows 上应从系统设置读取
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GestureThresholds {
+    /// 两次按下之间不超过这么久才算连击（毫秒）
+    pub double_click_time: u32,
+    /// 连击时两次按下的位置不能超出的矩形（宽、高，像素）
+    pub double_click_size: (i32, i32),
+    /// 按下后移动超出这个矩形才算拖动（宽、高，像素）
+    pub drag_size: (i32, i32),
+}
+
+impl GestureThresholds {
+    /// 与 Windows 的默认设置相同
+    pub const DEFAULT: GestureThresholds = GestureThresholds {
+        double_click_time: 500,
+        double_click_size: (4, 4),
+        drag_size: (4, 4),
+    };
+}
+
+impl Default for GestureThresholds {
+    fn default() -> Self {
+        GestureThresholds::DEFAULT
+    }
+}
+
+// 一次按下
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    x: i32,
+    y: i32,
+    time: u32,
+}
+
+/// 根据左键的按下和抬起事件识别手势。
+///
+/// 时间使用系统的毫秒计数（比如 `MSLLHOOKSTRUCT::time`），允许回绕。
+#[derive(Debug, Clone)]
+pub struct GestureTracker {
+    thresholds: GestureThresholds,
+    // 当前这次按下
+    press: Option<Press>,
+    // 上一次按下，用来判断连击
+    previous: Option<Press>,
+    // 当前连击到第几下
+    clicks: u32,
+}
+
+impl GestureTracker {
+    pub const fn new(thresholds: GestureThresholds) -> GestureTracker {
+        GestureTracker {
+            thresholds,
+            press: None,
+            previous: None,
+            clicks: 0,
+        }
+    }
+
+    pub fn button_down(&mut self, x: i32, y: i32, time: u32) {
+        let press = Press { x, y, time };
+        let (width, height) = self.thresholds.double_click_size;
+        let continues = self.previous.is_some_and(|previous| {
+            time.wrapping_sub(previous.time) <= self.thresholds.double_click_time
+                && within(previous, press, width, height)
+        });
+        // 第四下重新算作单击，和大多数编辑器的行为一致
+        self.clicks = if continues && self.clicks < 3 { self.clicks + 1 } else { 1 };
+        self.press = Some(press);
+    }
+
+    /// 左键抬起时调用，返回识别出的手势；没有见到对应的按下时返回 None
+    pub fn button_up(&mut self, x: i32, y: i32, time: u32) -> Option<Gesture> {
+        let press = self.press.take()?;
+        let (width, height) = self.thresholds.drag_size;
+        if !within(press, Press { x, y, time }, width, height) {
+            // 拖动打断连击
+            self.previous = None;
+            self.clicks = 0;
+            return Some(Gesture::Drag);
+        }
+        self.previous = Some(press);
+        Some(match self.clicks {
+            2 => Gesture::DoubleClick,
+            3 => Gesture::TripleClick,
+            _ => Gesture::Click,
+        })
+    }
+}
+
+// 两个点是否落在以第一个点为中心、给定宽高的矩形内
+fn within(a: Press, b: Press, width: i32, height: i32) -> bool {
+    (a.x - b.x).abs() <= width / 2 && (a.y - b.y).abs() <= height / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 在 (x, y) 按下再原地抬起
+    fn click(tracker: &mut GestureTracker, x: i32, y: i32, time: u32) -> Option<Gesture> {
+        tracker.button_down(x, y, time);
+        tracker.button_up(x, y, time + 50)
+    }
+
+    #[test]
+    fn single_double_triple_and_fourth_click() {
+        let mut tracker = GestureTracker::new(GestureThresholds::DEFAULT);
+        assert_eq!(click(&mut tracker, 100, 100, 1000), Some(Gesture::Click));
+        assert_eq!(click(&mut tracker, 100, 100, 1200), Some(Gesture::DoubleClick));
+        assert_eq!(click(&mut tracker, 101, 99, 1400), Some(Gesture::TripleClick));
+        // 第四下重新算作单击
+        assert_eq!(click(&mut tracker, 100, 100, 1600), Some(Gesture::Click));
+        assert_eq!(click(&mut tracker, 100, 100, 1800), Some(Gesture::DoubleClick));
+    }
+
+    #[test]
+    fn double_click_time_is_inclusive() {
+        let mut tracker = GestureTracker::new(GestureThresholds::DEFAULT);
+        click(&mut tracker, 0, 0, 1000);
+        assert_eq!(click(&mut tracker, 0, 0, 1500), Some(Gesture::DoubleClick));
+
+        let mut tracker = GestureTracker::new(GestureThresholds::DEFAULT);
+        click(&mut tracker, 0, 0, 1000);
+        assert_eq!(click(&mut tracker, 0, 0, 1501), Some(Gesture::Click));
+    }
+
+    #[test]
+    fn double_click_must_stay_in_rectangle() {
+        // 4x4 的矩形：每个方向最多偏 2 像素
+        let mut tracker = GestureTracker::new(GestureThresholds::DEFAULT);
+        click(&mut tracker, 10, 10, 0);
+        assert_eq!(click(&mut tracker, 12, 8, 100), Some(Gesture::DoubleClick));
+
+        let mut tracker = GestureTracker::new(GestureThresholds::DEFAULT);
+        click(&mut tracker, 10, 10, 0);
+        assert_eq!(click(&mut tracker, 13, 10, 100), Some(Gesture::Click));
+    }
+
+    #[test]
+    fn drag_threshold_and_drag_breaks_click_chain() {
+        let mut tracker = GestureTracker::new(GestureThresholds::DEFAULT);
+        tracker.button_down(0, 0, 0);
+        assert_eq!(tracker.button_up(2, 2, 100), Some(Gesture::Click));
+        tracker.button_down(0, 0, 200);
+        assert_eq!(tracker.button_up(0, 3, 300), Some(Gesture::Drag));
+        // 拖动之后紧接着的点击不算连击
+        assert_eq!(click(&mut tracker, 0, 0, 350), Some(Gesture::Click));
+    }
+
+    #[test]
+    fn up_without_down_and_time_wraparound() {
+        let mut tracker = GestureTracker::new(GestureThresholds::DEFAULT);
+        assert_eq!(tracker.button_up(0, 0, 0), None);
+
+        // 系统的毫秒计数回绕之后仍然能识别双击
+        click(&mut tracker, 0, 0, u32::MAX - 100);
+        assert_eq!(click(&mut tracker, 0, 0, 100), Some(Gesture::DoubleClick));
+    }
+
+    #[test]
+    fn only_click_does_not_select() {
+        assert!(!Gesture::Click.selects_text());
+        assert!(Gesture::Drag.selects_text());
+        assert!(Gesture::DoubleClick.selects_text());
+        assert!(Gesture::TripleClick.selects_text());
+    }
+}
@@ -0,0 +1,552 @@
+// --- 方法三：全局鼠标钩子 + 模拟按键 ---
+// 这是一个"黑科技"方法，有侵入性，并且需要 unsafe 代码。
+// 它会监听鼠标左键的操作，识别出拖动选择、双击、三击之后模拟 Ctrl+C，再从剪贴板读取。
+// 键盘选择（Shift+方向键、Ctrl+A 等）在修饰键松开时同样触发一次捕获。
+
+use crate::{
+    capture::{self, CaptureEvent, CaptureMode, CaptureSource, StopToken, WindowInfo},
+    clipboard_snapshot::ClipboardSnapshot,
+    clipboard_watch::{self, ChangeCounter, WaitStrategy},
+    copy_timing::CopyTimingStats,
+    gesture::{GestureThresholds, GestureTracker},
+    rules::{self, Action, RuleSet},
+};
+use arboard::Clipboard;
+use std::{
+    sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, Sender}},
+    thread,
+    time::{Duration, Instant},
+};
+use windows::Win32::{
+    Foundation::{LPARAM, LRESULT, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::{
+        Input::KeyboardAndMouse::{
+            GetAsyncKeyState, GetDoubleClickTime, SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT,
+            KEYEVENTF_KEYUP, VIRTUAL_KEY, VK_A, VK_C, VK_CONTROL, VK_DOWN, VK_END, VK_ESCAPE, VK_HOME,
+            VK_LCONTROL, VK_LEFT, VK_LSHIFT, VK_NEXT, VK_PRIOR, VK_RCONTROL, VK_RIGHT, VK_RSHIFT,
+            VK_SHIFT, VK_UP,
+        },
+        WindowsAndMessaging::{
+            CallNextHookEx, GetMessageW, SetWindowsHookExW, UnhookWindowsHookEx, HHOOK, MSG,
+            WH_MOUSE_LL, WM_LBUTTONUP, PostThreadMessageW, WM_USER, TranslateMessage, DispatchMessageW,
+            WM_QUIT, WM_KEYDOWN, WH_KEYBOARD_LL, KBDLLHOOKSTRUCT, WM_LBUTTONDOWN, MSLLHOOKSTRUCT,
+            WM_KEYUP, LLKHF_INJECTED,
+            GetSystemMetrics, SM_CXDOUBLECLK, SM_CYDOUBLECLK, SM_CXDRAG, SM_CYDRAG,
+        },
+    },
+    System::Console::{SetConsoleCtrlHandler, CTRL_C_EVENT, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT},
+};
+
+// 全局变量来存储钩子句柄和状态
+static mut MOUSE_HOOK: Option<HHOOK> = None;
+static mut KEYBOARD_HOOK: Option<HHOOK> = None;
+static mut GESTURES: GestureTracker = GestureTracker::new(GestureThresholds::DEFAULT);
+static mut MAIN_THREAD_ID: u32 = 0;
+static SHOULD_EXIT: AtomicBool = AtomicBool::new(false);
+static IS_SIMULATING_CTRL_C: AtomicBool = AtomicBool::new(false);
+// 用键盘改变了选区，等修饰键松开后捕获
+static KEYBOARD_SELECTION_PENDING: AtomicBool = AtomicBool::new(false);
+
+// 控制台信号处理函数
+unsafe extern "system" fn console_ctrl_handler(ctrl_type: u32) -> windows::Win32::Foundation::BOOL {
+    match ctrl_type {
+        CTRL_C_EVENT | CTRL_BREAK_EVENT => {
+            // 只有在程序模拟 Ctrl+C 时才拦截信号，否则让用户正常操作通过
+            if IS_SIMULATING_CTRL_C.load(Ordering::Relaxed) {
+                eprintln!("[调试] 拦截了程序模拟的 Ctrl+C 信号，防止程序退出");
+                windows::Win32::Foundation::BOOL::from(true) // 返回 TRUE 表示已处理该信号
+            } else {
+                eprintln!("[事件] 检测到用户的 Ctrl+C 操作，正在优雅退出...");
+                SHOULD_EXIT.store(true, Ordering::Relaxed);
+                // 发送退出消息到主线程
+                let _ = PostThreadMessageW(MAIN_THREAD_ID, WM_QUIT, WPARAM(0), LPARAM(0));
+                windows::Win32::Foundation::BOOL::from(true) // 返回 TRUE 表示我们已经处理了这个信号
+            }
+        }
+        CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => {
+            eprintln!("[事件] 检测到系统关闭信号，正在清理资源...");
+            SHOULD_EXIT.store(true, Ordering::Relaxed);
+            // 给程序一点时间来清理资源
+            thread::sleep(Duration::from_millis(100));
+            windows::Win32::Foundation::BOOL::from(true)
+        }
+        _ => windows::Win32::Foundation::BOOL::from(false), // 其他信号交给默认处理器
+    }
+}
+
+// 模拟按下和释放 Ctrl+C
+fn simulate_ctrl_c() {
+    // 设置标志，表示程序正在模拟 Ctrl+C
+    IS_SIMULATING_CTRL_C.store(true, Ordering::Relaxed);
+    
+    // 需要 unsafe 因为我们在调用系统 API
+    unsafe {
+        let inputs = &mut [
+            // Press LCtrl
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VK_LCONTROL,
+                        ..Default::default()
+                    },
+                },
+            },
+            // Press C
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VK_C,
+                        ..Default::default()
+                    },
+                },
+            },
+            // Release C
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VK_C,
+                        dwFlags: KEYEVENTF_KEYUP,
+                        ..Default::default()
+                    },
+                },
+            },
+            // Release LCtrl
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VK_LCONTROL,
+                        dwFlags: KEYEVENTF_KEYUP,
+                        ..Default::default()
+                    },
+                },
+            },
+        ];
+        SendInput(inputs, std::mem::size_of::<INPUT>() as i32);
+    }
+    
+    // 短暂延迟后清除标志，确保信号处理器有时间处理
+    thread::sleep(Duration::from_millis(50));
+    IS_SIMULATING_CTRL_C.store(false, Ordering::Relaxed);
+}
+
+// 按住 Shift 时会扩展选区的按键
+fn is_selection_key(vk: u32) -> bool {
+    [VK_LEFT, VK_RIGHT, VK_UP, VK_DOWN, VK_HOME, VK_END, VK_PRIOR, VK_NEXT]
+        .iter()
+        .any(|key| key.0 as u32 == vk)
+}
+
+fn is_shift(vk: u32) -> bool {
+    vk == VK_LSHIFT.0 as u32 || vk == VK_RSHIFT.0 as u32 || vk == VK_SHIFT.0 as u32
+}
+
+fn is_control(vk: u32) -> bool {
+    vk == VK_LCONTROL.0 as u32 || vk == VK_RCONTROL.0 as u32 || vk == VK_CONTROL.0 as u32
+}
+
+unsafe fn key_held(key: VIRTUAL_KEY) -> bool {
+    GetAsyncKeyState(key.0 as i32) < 0
+}
+
+// 键盘钩子的回调函数
+unsafe extern "system" fn low_level_keyboard_proc(
+    n_code: i32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    if n_code >= 0 {
+        let kbd_struct = *(l_param.0 as *const KBDLLHOOKSTRUCT);
+        let vk = kbd_struct.vkCode;
+        // 我们自己模拟的 Ctrl+C 也会经过这里，不能把它当成用户操作
+        let injected = kbd_struct.flags.0 & LLKHF_INJECTED.0 != 0;
+        match w_param.0 as u32 {
+            WM_KEYDOWN => {
+                if vk == VK_ESCAPE.0 as u32 {
+                    eprintln!("[事件] 检测到 ESC 键，准备退出...");
+                    SHOULD_EXIT.store(true, Ordering::Relaxed);
+                    let _ = PostThreadMessageW(MAIN_THREAD_ID, WM_QUIT, WPARAM(0), LPARAM(0));
+                    return LRESULT(1); // 阻止 ESC 键传递给其他应用
+                }
+                if !injected && !is_shift(vk) && !is_control(vk) {
+                    // Shift+方向键/Home/End/翻页 或 Ctrl+A 会改变选区；其他按键（比如直接输入）会取消选区
+                    let selects = (key_held(VK_SHIFT) && is_selection_key(vk))
+                        || (key_held(VK_CONTROL) && vk == VK_A.0 as u32);
+                    KEYBOARD_SELECTION_PENDING.store(selects, Ordering::Relaxed);
+                }
+            }
+            WM_KEYUP if !injected && (is_shift(vk) || is_control(vk)) => {
+                // 等 Shift 和 Ctrl 都松开再捕获，否则模拟的 Ctrl+C 会和用户按着的键混在一起。
+                // 钩子回调里正在松开的这个键仍被视为按下，所以只检查另一个修饰键
+                let other_held = if is_shift(vk) { key_held(VK_CONTROL) } else { key_held(VK_SHIFT) };
+                if !other_held && KEYBOARD_SELECTION_PENDING.swap(false, Ordering::Relaxed) {
+                    eprintln!("[事件] 检测到键盘选择。");
+                    let _ = PostThreadMessageW(MAIN_THREAD_ID, WM_USER + 1, WPARAM(0), LPARAM(0));
+                }
+            }
+            _ => {}
+        }
+    }
+    CallNextHookEx(KEYBOARD_HOOK.unwrap(), n_code, w_param, l_param)
+}
+
+// 鼠标钩子的回调函数
+// 这个函数会在每次鼠标事件发生时被 Windows 调用
+unsafe extern "system" fn low_level_mouse_proc(
+    n_code: i32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    if n_code >= 0 {
+        let message = w_param.0 as u32;
+        if message == WM_LBUTTONDOWN || message == WM_LBUTTONUP {
+            let info = *(l_param.0 as *const MSLLHOOKSTRUCT);
+            let tracker = &mut *std::ptr::addr_of_mut!(GESTURES);
+            if message == WM_LBUTTONDOWN {
+                tracker.button_down(info.pt.x, info.pt.y, info.time);
+            } else if let Some(gesture) = tracker.button_up(info.pt.x, info.pt.y, info.time) {
+                // 只有可能选中了文本的操作才去模拟复制，单击按钮、任务栏等直接放过
+                if gesture.selects_text() {
+                    eprintln!("[事件] 检测到{}。", gesture.describe());
+                    // 不在钩子回调中执行耗时操作，而是发送消息到主线程，再由主线程交给捕获线程
+                    let _ = PostThreadMessageW(MAIN_THREAD_ID, WM_USER + 1, WPARAM(0), LPARAM(0));
+                }
+            }
+        }
+    }
+    // 把事件传递给下一个钩子，否则整个系统会卡住！
+    CallNextHookEx(MOUSE_HOOK.unwrap(), n_code, w_param, l_param)
+}
+
+// 处理文本捕获的函数，在捕获线程中执行
+// 返回 false 表示事件接收端已经关闭，应当停止监听
+fn handle_text_capture(events: &Sender<CaptureEvent>, source: &GlobalHookSource, stats: &mut CopyTimingStats) -> bool {
+    let mut receiver_alive = true;
+    let wait = &source.wait;
+
+    // 0. 先看规则：在终端、密码管理器等窗口里模拟 Ctrl+C 是危险的
+    let window = WindowInfo::foreground();
+    if let Some(rule) = source.rules.matching_rule(window.as_ref()).filter(|rule| rule.action == Action::Deny) {
+        let title = window.as_ref().map_or("未知窗口", |w| w.title.as_str());
+        eprintln!("[规则] 已跳过 {}（{}）", title, rule);
+        return receiver_alive;
+    }
+    if !source.rules.allows(window.as_ref()) {
+        eprintln!("[规则] 默认规则为拒绝，已跳过。");
+        return receiver_alive;
+    }
+
+    // 1. 完整备份用户当前的剪贴板（所有格式）。备份失败就不去动剪贴板，宁可这次不捕获
+    let backup = match ClipboardSnapshot::capture() {
+        Ok(backup) => backup,
+        Err(e) => {
+            eprintln!("[错误] 备份剪贴板失败，跳过本次捕获: {}", e);
+            return receiver_alive;
+        }
+    };
+    eprintln!("[操作] 已备份用户剪贴板内容（{} 种格式）", backup.len());
+
+    // 目标程序决定了要等多久
+    let process = window.as_ref().and_then(|w| w.process.as_deref());
+
+    // 2. 记下剪贴板的变化计数，然后模拟 Ctrl+C
+    let mut counter = ChangeCounter::open()
+        .and_then(|mut counter| counter.current().map(|before| (counter, before)))
+        .map_err(|e| eprintln!("[警告] 剪贴板变化计数不可用（{}），改为比较内容。", e))
+        .ok();
+    eprintln!("[操作] 正在模拟 Ctrl+C...");
+    let started = Instant::now();
+    simulate_ctrl_c();
+
+    // 3. 等待目标应用把选中内容写进剪贴板：按退避间隔检查计数，一变就去读，
+    //    慢的程序在最长等待时间内也不会被漏掉。没有计数时只能固定等一会儿，再通过和备份比较来判断
+    let copied = match counter.as_mut() {
+        Some((counter, before)) => {
+            let strategy = stats.strategy_for(process, wait);
+            match counter.wait_for_change(*before, started, &strategy) {
+                Ok(change) => {
+                    if let Some(process) = process {
+                        stats.record(process, change.map(|c| c.latency));
+                    }
+                    if let Some(change) = change {
+                        eprintln!("[操作] 剪贴板在 {}ms 后更新", change.latency.as_millis());
+                    }
+                    Some(change.is_some())
+                }
+                Err(e) => {
+                    // 和没有计数时一样等够固定时长，立刻读取多半还是旧内容
+                    eprintln!("[警告] 读取剪贴板变化计数失败: {}", e);
+                    thread::sleep(stats.fixed_delay_for(process, wait).saturating_sub(started.elapsed()));
+                    None
+                }
+            }
+        }
+        None => {
+            let delay = stats.fixed_delay_for(process, wait);
+            thread::sleep(delay.saturating_sub(started.elapsed()));
+            None
+        }
+    };
+
+    // 4. 从剪贴板读取捕获的内容
+    let mut event = None;
+    if copied == Some(false) {
+        eprintln!("[结果] {}ms 内剪贴板没有变化，可能没有选中文本。", wait.max_latency.as_millis());
+    } else if clipboard_watch::clipboard_marked_sensitive() {
+        // 复制出来的是密码管理器标记过的内容，不读取
+        event = Some(CaptureEvent::marked_sensitive(CaptureMode::GlobalHook, window));
+    } else {
+        match Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(captured_text) if !captured_text.trim().is_empty() => {
+                // 计数变了就说明确实复制到了内容，哪怕和用户原来的剪贴板一模一样；
+                // 没有计数时只能假定与备份相同的内容不是这次复制的
+                if copied == Some(true) || backup.text().as_ref() != Some(&captured_text) {
+                    event = Some(CaptureEvent::new(CaptureMode::GlobalHook, captured_text, window));
+                } else {
+                    eprintln!("[结果] 检测到的内容与用户剪贴板相同，可能没有新的选中文本。");
+                }
+            }
+            Ok(_) => eprintln!("[结果] 剪贴板为空或只包含空白字符，可能没有选中文本。"),
+            Err(e) => eprintln!("[结果] 剪贴板中没有文本，可能没有选中文本: {:?}", e),
+        }
+    }
+
+    // 5. 原样恢复用户的剪贴板；原本为空时恢复成空剪贴板。
+    //    计数证明剪贴板没被动过时不要重写：重写会让计数变化、触发其他剪贴板监听和历史工具，
+    //    还会强制渲染延迟提供的格式
+    if needs_restore(copied) {
+        match backup.restore() {
+            Ok(()) if backup.is_empty() => eprintln!("[操作] 已清空剪贴板（用户原本为空）"),
+            Ok(()) => eprintln!("[操作] 已恢复用户剪贴板内容"),
+            Err(e) => eprintln!("[警告] 恢复用户剪贴板内容失败: {}", e),
+        }
+    }
+
+    // 6. 最后才读取浏览器网址：它可能要等几百毫秒，不能拖在模拟复制和恢复剪贴板之前
+    if let Some(mut event) = event {
+        event.window = event.window.map(WindowInfo::with_foreground_url);
+        receiver_alive = events.send(event).is_ok();
+    }
+    receiver_alive
+}
+
+// 模拟复制之后是否需要恢复剪贴板。`copied` 为 None 表示没有计数可用，不知道有没有变，只能恢复
+fn needs_restore(copied: Option<bool>) -> bool {
+    copied != Some(false)
+}
+
+/// 全局鼠标钩子捕获源：鼠标左键抬起后模拟 Ctrl+C 读取选中文本
+#[derive(Debug, Clone, Default)]
+pub struct GlobalHookSource {
+    /// 模拟复制之后如何等待目标程序写入剪贴板；实际使用时会按每个程序的历史耗时调整
+    pub wait: WaitStrategy,
+    /// 被拒绝的窗口里不会模拟 Ctrl+C
+    pub rules: RuleSet,
+}
+
+impl CaptureSource for GlobalHookSource {
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::GlobalHook
+    }
+
+    fn run(&mut self, events: &Sender<CaptureEvent>, stop: &StopToken) {
+        let mut stats = CopyTimingStats::new();
+        // 钩子线程只负责接收输入事件，模拟复制、等待和恢复剪贴板都在捕获线程里做。
+        // 钩子回调超过 LowLevelHooksTimeout 没有返回时，系统会悄悄卸载钩子，等待期间全系统的鼠标键盘也会卡住
+        let (requests, pending) = mpsc::channel();
+        thread::scope(|scope| {
+            let source = &*self;
+            let stats = &mut stats;
+            scope.spawn(move || run_capture_worker(pending, events, source, stats));
+            run_message_loop(requests, stop);
+        });
+        print_timing_summary(&stats);
+    }
+}
+
+// 捕获线程：逐个处理钩子线程转交过来的捕获请求，发送端关闭时退出
+fn run_capture_worker(
+    pending: Receiver<()>,
+    events: &Sender<CaptureEvent>,
+    source: &GlobalHookSource,
+    stats: &mut CopyTimingStats,
+) {
+    while pending.recv().is_ok() {
+        // 处理上一次捕获期间积压的请求（比如双击之后紧接着的三击）合并成一次
+        while pending.try_recv().is_ok() {}
+        if !handle_text_capture(events, source, stats) {
+            eprintln!("[状态] 事件接收端已关闭，正在停止监听...");
+            SHOULD_EXIT.store(true, Ordering::Relaxed);
+            unsafe {
+                let _ = PostThreadMessageW(MAIN_THREAD_ID, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+            break;
+        }
+    }
+}
+
+// 退出时打印各程序的复制耗时
+fn print_timing_summary(stats: &CopyTimingStats) {
+    for (process, timing) in stats.summary() {
+        if timing.samples == 0 {
+            eprintln!("[统计] {}: {} 次没有复制到内容", process, timing.timeouts);
+        } else {
+            eprintln!(
+                "[统计] {}: 成功 {} 次，平均 {}ms，最快 {}ms，最慢 {}ms，超时 {} 次",
+                process,
+                timing.samples,
+                timing.average.as_millis(),
+                timing.fastest.as_millis(),
+                timing.slowest.as_millis(),
+                timing.timeouts
+            );
+        }
+    }
+}
+
+pub fn run() {
+    println!("方法三：全局鼠标钩子模式已启动。");
+    println!("请在任何地方用鼠标选中一段文本，然后松开左键。");
+    println!("✅ 改进：程序会自动备份和恢复你的剪贴板内容（包括图片、格式文本和文件），不影响正常使用");
+    println!("提示：只有拖动选择、双击和三击会触发捕获，普通单击会被忽略。");
+    println!("也可以用键盘选择（Shift+方向键、Shift+Home/End、Ctrl+A），松开 Shift/Ctrl 后自动捕获。");
+    println!("退出方式：按 ESC 键退出，或关闭此控制台窗口");
+
+    capture::run_and_print(GlobalHookSource {
+        rules: rules::load_default_or_builtin(),
+        ..GlobalHookSource::default()
+    });
+}
+
+// 读取系统的双击间隔、双击范围和拖动阈值
+unsafe fn system_gesture_thresholds() -> GestureThresholds {
+    GestureThresholds {
+        double_click_time: GetDoubleClickTime(),
+        double_click_size: (GetSystemMetrics(SM_CXDOUBLECLK), GetSystemMetrics(SM_CYDOUBLECLK)),
+        drag_size: (GetSystemMetrics(SM_CXDRAG), GetSystemMetrics(SM_CYDRAG)),
+    }
+}
+
+// 安装钩子并运行消息循环，钩子必须和消息循环在同一个线程。
+// 消息循环只把捕获请求转交给捕获线程，自己从不阻塞；返回时 `requests` 被丢弃，捕获线程随之退出
+fn run_message_loop(requests: Sender<()>, stop: &StopToken) {
+    // 重置退出标志
+    SHOULD_EXIT.store(false, Ordering::Relaxed);
+
+    // 需要 unsafe 因为我们在设置一个全局钩子
+    unsafe {
+        // 设置控制台信号处理器，防止模拟的 Ctrl+C 导致程序退出
+        if let Err(e) = SetConsoleCtrlHandler(Some(console_ctrl_handler), true) {
+            eprintln!("[警告] 设置控制台信号处理器失败: {:?}", e);
+        } else {
+            eprintln!("[状态] 控制台信号处理器已设置，程序不会因模拟 Ctrl+C 而退出。");
+        }
+        // 获取当前线程ID
+        MAIN_THREAD_ID = GetCurrentThreadId();
+        // 按系统的双击和拖动设置识别手势
+        GESTURES = GestureTracker::new(system_gesture_thresholds());
+
+        // 外部调用 stop 时，向消息循环发送 WM_QUIT 把 GetMessageW 唤醒
+        let loop_thread_id = MAIN_THREAD_ID;
+        stop.on_stop(move || {
+            let _ = PostThreadMessageW(loop_thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        });
+        
+        // 设置键盘钩子用于检测 ESC 键
+        let keyboard_hook = match SetWindowsHookExW(
+            WH_KEYBOARD_LL,
+            Some(low_level_keyboard_proc),
+            None,
+            0,
+        ) {
+            Ok(h) => h,
+            Err(e) => {
+                eprintln!("[错误] 设置键盘钩子失败: {:?}", e);
+                return;
+            }
+        };
+        KEYBOARD_HOOK = Some(keyboard_hook);
+        
+        // 设置一个低级鼠标钩子
+        let mouse_hook = match SetWindowsHookExW(
+            WH_MOUSE_LL,
+            Some(low_level_mouse_proc),
+            None, // hmod: None 表示钩子与任何特定模块无关
+            0,    // dwThreadId: 0 表示这是一个全局钩子
+        ) {
+            Ok(h) => h,
+            Err(e) => {
+                eprintln!("[错误] 设置鼠标钩子失败: {:?}", e);
+                // 如果鼠标钩子失败，也要清理键盘钩子
+                let _ = UnhookWindowsHookEx(keyboard_hook);
+                return;
+            }
+        };
+        MOUSE_HOOK = Some(mouse_hook);
+
+        eprintln!("[状态] 鼠标和键盘钩子已成功安装，开始监听...");
+
+        // 运行一个消息循环，这是接收钩子事件所必需的
+        eprintln!("[状态] 钩子已激活，开始持续监听鼠标事件...");
+        eprintln!("[提示] 现在可以在任何地方选中文本并松开鼠标左键进行捕获。");
+        
+        let mut msg: MSG = Default::default();
+        loop {
+            // 检查是否需要退出
+            if SHOULD_EXIT.load(Ordering::Relaxed) || stop.is_stopped() {
+                eprintln!("[状态] 检测到退出信号，正在停止监听...");
+                break;
+            }
+            
+            let result = GetMessageW(&mut msg, None, 0, 0);
+            
+            // 检查是否收到退出消息
+            if !result.as_bool() || msg.message == WM_QUIT {
+                eprintln!("[状态] 收到系统退出信号，正在停止监听...");
+                break;
+            }
+            
+            // 检查是否是我们的自定义消息
+            if msg.message == WM_USER + 1 {
+                // 暂停期间不模拟按键
+                if stop.is_paused() {
+                    continue;
+                }
+                let _ = requests.send(());
+            } else {
+                // 处理其他消息
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+
+        // 程序退出前，卸载钩子
+        if let Err(e) = UnhookWindowsHookEx(mouse_hook) {
+            eprintln!("[警告] 卸载鼠标钩子时出错: {:?}", e);
+        } else {
+            eprintln!("[状态] 鼠标钩子已成功卸载。");
+        }
+        
+        if let Err(e) = UnhookWindowsHookEx(keyboard_hook) {
+            eprintln!("[警告] 卸载键盘钩子时出错: {:?}", e);
+        } else {
+            eprintln!("[状态] 键盘钩子已成功卸载。");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_only_when_clipboard_may_have_changed() {
+        assert!(needs_restore(Some(true)));
+        assert!(needs_restore(None));
+        assert!(!needs_restore(Some(false)));
+    }
+}
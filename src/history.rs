@@ -0,0 +1,728 @@
+// --- 捕获历史 ---
+// 所有模式的捕获结果都会写进本地的 SQLite 数据库，几个小时之后还能回头查看。
+// 每条记录保存文本、捕获模式、内容类型（选中的文本还是输入框内容）、时间、来源窗口（标题、类名、进程、网址）和内容哈希（SHA-256），
+// 哈希可以用来快速找出重复捕获的内容。HTML、图片等非纯文本内容另外保存 MIME 类型和原始数据，
+// text 列存放它们的文本表示，所以同样可以被检索。
+//
+// 全文检索使用 SQLite FTS5 的 trigram 分词器：中文没有空格分词，
+// trigram 可以匹配任意位置的子串（至少 3 个字符），短于 3 个字符的关键词退回到 LIKE 匹配。
+
+use crate::capture::{CaptureEvent, CaptureKind, CaptureMode};
+use rusqlite::{params, Connection, Row};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub use rusqlite::{Error, Result};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS captures (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    text         TEXT    NOT NULL,
+    mode         TEXT    NOT NULL,
+    captured_at  INTEGER NOT NULL, -- Unix 时间戳，毫秒
+    window_title TEXT,
+    content_hash TEXT    NOT NULL,
+    mime_type    TEXT    NOT NULL DEFAULT 'text/plain',
+    data         BLOB,             -- 非纯文本内容的原始数据，图片为 PNG
+    process      TEXT,             -- 可执行文件名
+    pid          INTEGER,
+    exe_path     TEXT,
+    window_class TEXT,
+    url          TEXT,             -- 浏览器当前标签页的网址
+    kind         TEXT    NOT NULL DEFAULT 'selection' -- 选中的文本，还是输入框的全部内容（field_value）
+);
+CREATE INDEX IF NOT EXISTS captures_captured_at ON captures (captured_at);
+CREATE INDEX IF NOT EXISTS captures_content_hash ON captures (content_hash);
+";
+
+// 依赖后加的列的索引，补完列之后再建
+const ADDED_INDEXES: &str = "
+CREATE INDEX IF NOT EXISTS captures_process ON captures (process COLLATE NOCASE);
+";
+
+// 读取 HistoryEntry 需要的列
+const ENTRY_COLUMNS: &str = "id, text, mode, captured_at, window_title, content_hash, mime_type,
+    process, pid, exe_path, window_class, url, kind";
+
+// 早期版本的数据库没有这些列，打开时补上
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("mime_type", "TEXT NOT NULL DEFAULT 'text/plain'"),
+    ("data", "BLOB"),
+    ("process", "TEXT"),
+    ("pid", "INTEGER"),
+    ("exe_path", "TEXT"),
+    ("window_class", "TEXT"),
+    ("url", "TEXT"),
+    ("kind", "TEXT NOT NULL DEFAULT 'selection'"),
+];
+
+// 全文索引与 captures 表通过触发器保持同步
+const FTS_SCHEMA: &str = "
+CREATE VIRTUAL TABLE captures_fts USING fts5 (
+    text, window_title,
+    content = 'captures', content_rowid = 'id',
+    tokenize = 'trigram'
+);
+CREATE TRIGGER captures_fts_insert AFTER INSERT ON captures BEGIN
+    INSERT INTO captures_fts (rowid, text, window_title) VALUES (new.id, new.text, new.window_title);
+END;
+CREATE TRIGGER captures_fts_delete AFTER DELETE ON captures BEGIN
+    INSERT INTO captures_fts (captures_fts, rowid, text, window_title)
+    VALUES ('delete', old.id, old.text, old.window_title);
+END;
+-- 为已有的历史记录建立索引
+INSERT INTO captures_fts (captures_fts) VALUES ('rebuild');
+";
+
+// trigram 分词器能匹配的最短关键词长度（按字符计）
+const MIN_FTS_KEYWORD_CHARS: usize = 3;
+
+/// 历史记录中的一条捕获
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub text: String,
+    pub mode: CaptureMode,
+    pub timestamp: SystemTime,
+    pub window_title: Option<String>,
+    pub content_hash: String,
+    pub mime_type: String,
+    pub process: Option<String>,
+    pub pid: Option<u32>,
+    pub exe_path: Option<String>,
+    pub window_class: Option<String>,
+    pub url: Option<String>,
+    pub kind: CaptureKind,
+}
+
+impl HistoryEntry {
+    fn from_row(row: &Row<'_>) -> Result<HistoryEntry> {
+        let mode: String = row.get("mode")?;
+        let captured_at: i64 = row.get("captured_at")?;
+        let kind: String = row.get("kind")?;
+        Ok(HistoryEntry {
+            id: row.get("id")?,
+            text: row.get("text")?,
+            // 未知的模式名只可能来自更新版本写入的数据库，按剪贴板处理
+            mode: mode.parse().unwrap_or(CaptureMode::ClipboardPoll),
+            timestamp: from_unix_millis(captured_at),
+            window_title: row.get("window_title")?,
+            content_hash: row.get("content_hash")?,
+            mime_type: row.get("mime_type")?,
+            process: row.get("process")?,
+            pid: row.get("pid")?,
+            exe_path: row.get("exe_path")?,
+            window_class: row.get("window_class")?,
+            url: row.get("url")?,
+            // 同样，未知的类型按选中文本处理
+            kind: kind.parse().unwrap_or_default(),
+        })
+    }
+
+    /// 转成 JSON 对象，字段与捕获事件的 JSON 输出保持一致
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "text": self.text,
+            "mode": self.mode.as_str(),
+            "kind": self.kind.as_str(),
+            "timestamp": crate::capture::rfc3339(self.timestamp),
+            "window": self.window_title,
+            "process": self.process,
+            "window_class": self.window_class,
+            "pid": self.pid,
+            "exe_path": self.exe_path,
+            "url": self.url,
+            "mime_type": self.mime_type,
+        })
+    }
+}
+
+/// 捕获历史数据库
+pub struct HistoryStore {
+    conn: Connection,
+}
+
+impl HistoryStore {
+    /// 打开（必要时创建）指定路径的历史数据库
+    pub fn open(path: &Path) -> Result<HistoryStore> {
+        if let Some(dir) = path.parent() {
+            // 目录创建失败时让 SQLite 报告更具体的错误
+            let _ = std::fs::create_dir_all(dir);
+        }
+        HistoryStore::init(Connection::open(path)?)
+    }
+
+    /// 打开默认位置的历史数据库，见 `default_path`
+    pub fn open_default() -> Result<HistoryStore> {
+        HistoryStore::open(&default_path())
+    }
+
+    /// 仅存在于内存中的数据库，关闭后数据即丢失
+    pub fn open_in_memory() -> Result<HistoryStore> {
+        HistoryStore::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<HistoryStore> {
+        // 多个模式可能同时写入同一个数据库，遇到锁时等待而不是立即失败
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(SCHEMA)?;
+        for (column, definition) in ADDED_COLUMNS {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info('captures') WHERE name = ?1)",
+                params![column],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE captures ADD COLUMN {} {}", column, definition))?;
+            }
+        }
+        conn.execute_batch(ADDED_INDEXES)?;
+
+        let has_fts: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'captures_fts')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_fts {
+            conn.execute_batch(&format!("BEGIN; {} COMMIT;", FTS_SCHEMA))?;
+        }
+        Ok(HistoryStore { conn })
+    }
+
+    /// 保存一次捕获，返回新记录的 id
+    pub fn insert(&self, event: &CaptureEvent) -> Result<i64> {
+        let window = event.window.as_ref();
+        self.conn.execute(
+            "INSERT INTO captures (text, mode, captured_at, window_title, content_hash, mime_type, data,
+                                   process, pid, exe_path, window_class, url, kind)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                event.text,
+                event.mode.as_str(),
+                to_unix_millis(event.timestamp),
+                window.map(|w| w.title.as_str()),
+                // 有原始数据时按原始数据计算，否则不同的图片会因为文本表示相同而被当成重复
+                match &event.data {
+                    Some(data) => content_hash(data),
+                    None => content_hash(&event.text),
+                },
+                event.mime_type,
+                event.data,
+                window.and_then(|w| w.process.as_deref()),
+                window.and_then(|w| w.pid),
+                window.and_then(|w| w.exe_path.as_ref()).map(|p| p.to_string_lossy()),
+                window.and_then(|w| w.class.as_deref()),
+                window.and_then(|w| w.url.as_deref()),
+                event.kind.as_str(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// 最近的 `limit` 条捕获，最新的在前
+    pub fn recent(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM captures ORDER BY captured_at DESC, id DESC LIMIT ?1",
+            ENTRY_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![limit as i64], HistoryEntry::from_row)?;
+        rows.collect()
+    }
+
+    /// 按关键词、时间范围、捕获模式、来源窗口和来源程序搜索历史。
+    ///
+    /// 有关键词时按 BM25 相关度排序，相关度相同的较新的在前；只有过滤条件时按时间倒序。
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let (fts_terms, like_terms): (Vec<&String>, Vec<&String>) = query
+            .keywords
+            .iter()
+            .filter(|k| !k.trim().is_empty())
+            .partition(|k| k.chars().count() >= MIN_FTS_KEYWORD_CHARS);
+        let use_fts = !fts_terms.is_empty();
+
+        let mut conditions = Vec::new();
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let mut bind = |value: rusqlite::types::Value| {
+            values.push(value);
+            format!("?{}", values.len())
+        };
+
+        if use_fts {
+            // 每个关键词作为一个带引号的短语，多个关键词之间是 AND 关系
+            let expr = fts_terms
+                .iter()
+                .map(|k| format!("\"{}\"", k.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            conditions.push(format!("captures_fts MATCH {}", bind(expr.into())));
+        }
+        for term in like_terms {
+            let pattern = format!("%{}%", escape_like(term));
+            let param = bind(pattern.into());
+            conditions.push(format!(
+                "(c.text LIKE {p} ESCAPE '\\' OR c.window_title LIKE {p} ESCAPE '\\')",
+                p = param
+            ));
+        }
+        if let Some(since) = query.since {
+            conditions.push(format!("c.captured_at >= {}", bind(to_unix_millis(since).into())));
+        }
+        if let Some(until) = query.until {
+            conditions.push(format!("c.captured_at < {}", bind(to_unix_millis(until).into())));
+        }
+        if let Some(mode) = query.mode {
+            conditions.push(format!("c.mode = {}", bind(mode.as_str().to_string().into())));
+        }
+        if let Some(window) = &query.window {
+            let pattern = format!("%{}%", escape_like(window));
+            conditions.push(format!("c.window_title LIKE {} ESCAPE '\\'", bind(pattern.into())));
+        }
+        if let Some(process) = &query.process {
+            conditions.push(format!("c.process = {} COLLATE NOCASE", bind(process.clone().into())));
+        }
+
+        let (open, close) = (&query.highlight.0, &query.highlight.1);
+        let (snippet, rank, from, order) = if use_fts {
+            (
+                format!(
+                    "snippet(captures_fts, 0, {}, {}, '…', {})",
+                    bind(open.clone().into()),
+                    bind(close.clone().into()),
+                    bind((query.snippet_tokens as i64).into())
+                ),
+                "bm25(captures_fts)",
+                "captures_fts JOIN captures c ON c.id = captures_fts.rowid",
+                "rank, c.captured_at DESC",
+            )
+        } else {
+            ("c.text".to_string(), "0.0", "captures c", "c.captured_at DESC, c.id DESC")
+        };
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let limit = bind((query.limit as i64).into());
+
+        let sql = format!(
+            "SELECT c.id, c.text, c.mode, c.captured_at, c.window_title, c.content_hash, c.mime_type,
+                    c.process, c.pid, c.exe_path, c.window_class, c.url, c.kind,
+                    {snippet} AS snippet, {rank} AS rank
+             FROM {from} {where_clause}
+             ORDER BY {order} LIMIT {limit}"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            let entry = HistoryEntry::from_row(row)?;
+            let snippet: String = row.get("snippet")?;
+            Ok(SearchHit {
+                // 没有走全文索引时，在 Rust 里补上短关键词的高亮
+                snippet: if use_fts {
+                    snippet
+                } else {
+                    like_snippet(&snippet, &query.keywords, open, close, query.snippet_tokens)
+                },
+                rank: row.get("rank")?,
+                entry,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 一条记录的原始数据（HTML 源码、PNG 图片等）；纯文本记录返回 None
+    pub fn data(&self, id: i64) -> Result<Option<Vec<u8>>> {
+        self.conn
+            .query_row("SELECT data FROM captures WHERE id = ?1", params![id], |row| row.get(0))
+    }
+
+    /// 按 id 读取一条记录
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM captures WHERE id = ?1", ENTRY_COLUMNS))?;
+        let mut rows = stmt.query_map(params![id], HistoryEntry::from_row)?;
+        rows.next().transpose()
+    }
+
+    /// 按来源程序分组统计捕获，次数多的在前；没有来源程序的捕获归为 None
+    pub fn sources(&self) -> Result<Vec<SourceStats>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT process, COUNT(*) AS captures, MAX(captured_at) AS last_captured_at
+             FROM captures GROUP BY process COLLATE NOCASE
+             ORDER BY captures DESC, last_captured_at DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SourceStats {
+                process: row.get("process")?,
+                captures: row.get::<_, i64>("captures")? as u64,
+                last_captured: from_unix_millis(row.get("last_captured_at")?),
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 记录总数
+    pub fn count(&self) -> Result<u64> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM captures", [], |row| row.get::<_, i64>(0))
+            .map(|n| n as u64)
+    }
+}
+
+/// 历史搜索条件，所有条件之间是 AND 关系
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    /// 关键词，每条记录必须包含全部关键词
+    pub keywords: Vec<String>,
+    /// 只返回此时间之后（含）的捕获
+    pub since: Option<SystemTime>,
+    /// 只返回此时间之前的捕获
+    pub until: Option<SystemTime>,
+    pub mode: Option<CaptureMode>,
+    /// 来源窗口标题包含的文本
+    pub window: Option<String>,
+    /// 来源程序的可执行文件名，不区分大小写
+    pub process: Option<String>,
+    pub limit: usize,
+    /// 摘要里包围命中关键词的标记
+    pub highlight: (String, String),
+    /// 摘要的最大长度（trigram 分词下约等于字符数）
+    pub snippet_tokens: usize,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        SearchQuery {
+            keywords: Vec::new(),
+            since: None,
+            until: None,
+            mode: None,
+            window: None,
+            process: None,
+            limit: 20,
+            highlight: ("【".to_string(), "】".to_string()),
+            snippet_tokens: 32,
+        }
+    }
+}
+
+/// 一条搜索结果
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub entry: HistoryEntry,
+    /// 带高亮标记的摘要
+    pub snippet: String,
+    /// BM25 得分，越小越相关；没有关键词时为 0
+    pub rank: f64,
+}
+
+/// 一个来源程序的捕获统计
+#[derive(Debug, Clone)]
+pub struct SourceStats {
+    pub process: Option<String>,
+    pub captures: u64,
+    pub last_captured: SystemTime,
+}
+
+/// 默认的历史数据库位置。
+///
+/// 可以用环境变量 `TEXT_LISTENER_HISTORY` 指定；否则 Windows 上放在 `%APPDATA%\text_listener`，
+/// 其他平台放在 `$XDG_DATA_HOME/text_listener`（默认 `~/.local/share/text_listener`）。
+pub fn default_path() -> PathBuf {
+    if let Some(path) = env::var_os("TEXT_LISTENER_HISTORY") {
+        return PathBuf::from(path);
+    }
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.unwrap_or_else(|| PathBuf::from("."))
+        .join("text_listener")
+        .join("history.db")
+}
+
+/// 内容的 SHA-256，十六进制小写
+pub fn content_hash(content: impl AsRef<[u8]>) -> String {
+    Sha256::digest(content.as_ref())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn to_unix_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn from_unix_millis(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+}
+
+// 转义 LIKE 模式中的通配符
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+// 没有走全文索引时的摘要：截取第一个关键词附近最多 max_chars 个字符，截断处加省略号
+fn like_snippet(text: &str, keywords: &[String], open: &str, close: &str, max_chars: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    // 关键词可能只出现在窗口标题里，这时从开头截取
+    let first = keyword_at_each_char(&chars, keywords).find_map(|(i, len)| len.map(|len| (i, len)));
+    let (at, len) = first.unwrap_or((0, 0));
+    // 关键词放在中间，靠近末尾时向前多取一些
+    let width = max_chars.max(len);
+    let end = (at.saturating_sub((width - len) / 2) + width).min(chars.len());
+    let start = end.saturating_sub(width);
+
+    let mut result = String::new();
+    if start > 0 {
+        result.push('…');
+    }
+    result.push_str(&highlight(&chars[start..end].iter().collect::<String>(), keywords, open, close));
+    if end < chars.len() {
+        result.push('…');
+    }
+    result
+}
+
+// 给文本中出现的关键词加上高亮标记。只扫描一遍，命中的范围互不重叠，同一位置优先取最长的关键词
+fn highlight(text: &str, keywords: &[String], open: &str, close: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut skip_until = 0;
+    for (i, len) in keyword_at_each_char(&chars, keywords) {
+        if i < skip_until {
+            continue;
+        }
+        match len {
+            Some(len) => {
+                result.push_str(open);
+                result.extend(&chars[i..i + len]);
+                result.push_str(close);
+                skip_until = i + len;
+            }
+            None => result.push(chars[i]),
+        }
+    }
+    result
+}
+
+// 每个字符位置上开始的最长关键词的长度（字符数）
+fn keyword_at_each_char<'a>(
+    chars: &'a [char],
+    keywords: &'a [String],
+) -> impl Iterator<Item = (usize, Option<usize>)> + 'a {
+    let keywords: Vec<Vec<char>> = keywords
+        .iter()
+        .filter(|k| !k.trim().is_empty())
+        .map(|k| k.chars().collect())
+        .collect();
+    (0..chars.len()).map(move |i| {
+        let len = keywords
+            .iter()
+            .filter(|k| chars[i..].starts_with(k))
+            .map(|k| k.len())
+            .max();
+        (i, len)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::WindowInfo;
+
+    #[test]
+    fn kind_is_saved_and_read_back() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let selection = CaptureEvent::new(CaptureMode::UiAutomation, "选中的文本".to_string(), None);
+        let field = CaptureEvent {
+            kind: CaptureKind::FieldValue,
+            ..CaptureEvent::new(CaptureMode::UiAutomation, "输入框里的全部内容".to_string(), None)
+        };
+        let selection_id = store.insert(&selection).unwrap();
+        let field_id = store.insert(&field).unwrap();
+
+        assert_eq!(store.get(selection_id).unwrap().unwrap().kind, CaptureKind::Selection);
+        let entry = store.get(field_id).unwrap().unwrap();
+        assert_eq!(entry.kind, CaptureKind::FieldValue);
+        assert_eq!(entry.to_json()["kind"], "field_value");
+    }
+
+    #[test]
+    fn old_database_gets_kind_column() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE captures (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, text TEXT NOT NULL, mode TEXT NOT NULL,
+                captured_at INTEGER NOT NULL, window_title TEXT, content_hash TEXT NOT NULL
+            );
+            INSERT INTO captures (text, mode, captured_at, content_hash) VALUES ('旧记录', 'clipboard', 0, '');",
+        )
+        .unwrap();
+        let store = HistoryStore::init(conn).unwrap();
+        let entry = store.recent(1).unwrap().remove(0);
+        assert_eq!(entry.text, "旧记录");
+        assert_eq!(entry.kind, CaptureKind::Selection);
+    }
+
+    #[test]
+    fn highlight_does_not_nest_overlapping_keywords() {
+        let keywords = ["ab".to_string(), "abc".to_string(), "c".to_string()];
+        assert_eq!(highlight("abc ab c", &keywords, "[", "]"), "[abc] [ab] [c]");
+        // 关键词本身包含标记字符时不会被再次替换
+        let keywords = ["a".to_string(), "[".to_string()];
+        assert_eq!(highlight("a[", &keywords, "[", "]"), "[a][[]");
+    }
+
+    #[test]
+    fn like_snippet_is_cut_around_first_match() {
+        let text = format!("{}关键{}", "前".repeat(20), "后".repeat(20));
+        let keywords = ["关键".to_string()];
+        let snippet = like_snippet(&text, &keywords, "【", "】", 10);
+        assert_eq!(snippet, "…前前前前【关键】后后后后…");
+        // 靠近开头和末尾时不加省略号，也不会少取
+        assert_eq!(like_snippet("关键在开头的文本", &keywords, "【", "】", 4), "【关键】在开…");
+        assert_eq!(like_snippet("文本的末尾是关键", &keywords, "【", "】", 4), "…尾是【关键】");
+        // 只在窗口标题里命中时从开头截取
+        assert_eq!(like_snippet("没有命中", &keywords, "【", "】", 2), "没有…");
+    }
+
+    #[test]
+    fn short_keyword_search_gets_snippet() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let text = format!("{}猫{}", "很长的开头。".repeat(20), "很长的结尾。".repeat(20));
+        store.insert(&CaptureEvent::new(CaptureMode::ClipboardPoll, text, None)).unwrap();
+        let hits = store
+            .search(&SearchQuery {
+                keywords: vec!["猫".to_string()],
+                snippet_tokens: 9,
+                ..SearchQuery::default()
+            })
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].snippet, "…的开头。【猫】很长的结…");
+    }
+
+    // 在 `secs` 秒（Unix 时间）捕获的一条记录
+    fn insert_at(store: &HistoryStore, mode: CaptureMode, text: &str, secs: u64, window: Option<(&str, &str)>) -> i64 {
+        let window = window.map(|(title, process)| WindowInfo {
+            title: title.to_string(),
+            process: Some(process.to_string()),
+            class: None,
+            pid: None,
+            exe_path: None,
+            url: None,
+        });
+        let event = CaptureEvent {
+            timestamp: UNIX_EPOCH + Duration::from_secs(secs),
+            ..CaptureEvent::new(mode, text.to_string(), window)
+        };
+        store.insert(&event).unwrap()
+    }
+
+    fn search_ids(store: &HistoryStore, query: SearchQuery) -> Vec<i64> {
+        store.search(&query).unwrap().iter().map(|hit| hit.entry.id).collect()
+    }
+
+    fn keywords(keywords: &[&str]) -> SearchQuery {
+        SearchQuery {
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            ..SearchQuery::default()
+        }
+    }
+
+    #[test]
+    fn fts_matches_all_long_keywords() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let both = insert_at(&store, CaptureMode::ClipboardPoll, "今天天气很好，适合出门散步", 1, None);
+        let one = insert_at(&store, CaptureMode::ClipboardPoll, "今天天气很差", 2, None);
+        insert_at(&store, CaptureMode::ClipboardPoll, "明天会下雨", 3, None);
+
+        assert_eq!(search_ids(&store, keywords(&["天气很"])), [one, both]);
+        assert_eq!(search_ids(&store, keywords(&["天气很", "出门散步"])), [both]);
+        assert!(search_ids(&store, keywords(&["不存在的词"])).is_empty());
+        // 英文按 trigram 匹配任意位置的子串，不区分大小写
+        let english = insert_at(&store, CaptureMode::ClipboardPoll, "Rust ownership rules", 4, None);
+        assert_eq!(search_ids(&store, keywords(&["OWNER"])), [english]);
+    }
+
+    #[test]
+    fn fts_ranks_by_bm25() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let dense = insert_at(&store, CaptureMode::ClipboardPoll, "剪贴板 剪贴板 剪贴板", 1, None);
+        let sparse = insert_at(
+            &store,
+            CaptureMode::ClipboardPoll,
+            "这是一段很长的文本，只在最后提到了一次剪贴板，前面都是无关的内容，用来拉低相关度。",
+            2,
+            None,
+        );
+        // 较新的记录相关度更低，排在后面
+        let hits = store.search(&keywords(&["剪贴板"])).unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.entry.id).collect::<Vec<_>>(), [dense, sparse]);
+        assert!(hits[0].rank < hits[1].rank);
+    }
+
+    #[test]
+    fn fts_snippet_is_highlighted() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        insert_at(&store, CaptureMode::ClipboardPoll, "请把这段文本复制到剪贴板里", 1, None);
+        let hits = store
+            .search(&SearchQuery {
+                highlight: ("<b>".to_string(), "</b>".to_string()),
+                ..keywords(&["剪贴板"])
+            })
+            .unwrap();
+        assert_eq!(hits[0].snippet, "请把这段文本复制到<b>剪贴板</b>里");
+    }
+
+    #[test]
+    fn filters_by_time_mode_window_and_process() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let early = insert_at(&store, CaptureMode::ClipboardPoll, "第一条", 100, Some(("笔记 - 记事本", "notepad.exe")));
+        let middle = insert_at(&store, CaptureMode::GlobalHook, "第二条", 200, Some(("Rust 文档 - Chrome", "chrome.exe")));
+        let late = insert_at(&store, CaptureMode::ClipboardPoll, "第三条", 300, None);
+        let at = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+
+        // since 包含边界，until 不包含
+        let query = SearchQuery {
+            since: at(200),
+            ..SearchQuery::default()
+        };
+        assert_eq!(search_ids(&store, query), [late, middle]);
+        let query = SearchQuery {
+            until: at(200),
+            ..SearchQuery::default()
+        };
+        assert_eq!(search_ids(&store, query), [early]);
+        let query = SearchQuery {
+            mode: Some(CaptureMode::ClipboardPoll),
+            ..SearchQuery::default()
+        };
+        assert_eq!(search_ids(&store, query), [late, early]);
+        let query = SearchQuery {
+            window: Some("文档".to_string()),
+            ..SearchQuery::default()
+        };
+        assert_eq!(search_ids(&store, query), [middle]);
+        let query = SearchQuery {
+            process: Some("NOTEPAD.EXE".to_string()),
+            ..SearchQuery::default()
+        };
+        assert_eq!(search_ids(&store, query), [early]);
+        // 过滤条件和关键词同时生效
+        let query = SearchQuery {
+            mode: Some(CaptureMode::GlobalHook),
+            ..keywords(&["第三条"])
+        };
+        assert!(search_ids(&store, query).is_empty());
+    }
+}
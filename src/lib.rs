@@ -0,0 +1,41 @@
+// text_listener：从其他程序里"听"到用户复制或选中的文本。
+//
+// 提供四种捕获模式，它们都实现了 `capture::CaptureSource`：
+// - 方法一 `clipboard_poller`：轮询剪贴板，跨平台
+// - 方法二 `ui_automation_improved`：Windows UI Automation；Linux 上对应的是 `atspi`（AT-SPI2）
+// - 方法三 `global_hook_simulator`：全局鼠标钩子 + 模拟 Ctrl+C
+// - 方法四 `primary_selection`：监听 PRIMARY selection（X11 / Wayland），仅 Linux
+//
+// 剪贴板里的 HTML、图片和文件列表由 `clipboard_content` 统一表示。
+// 所有捕获都可以通过 `history` 保存到本地 SQLite 数据库，`rules` 决定哪些窗口允许捕获，
+// `redact` 在输出和保存之前隐藏密码、密钥等敏感内容。
+// `server` 提供一个只监听本机的 HTTP 接口，其他程序可以通过 SSE 或 WebSocket 实时接收捕获。
+//
+// 嵌入到其他程序时，用 `capture::start` 在后台线程启动任意捕获源，
+// 通过返回的 `CaptureHandle` 读取事件或者停止监听。
+
+pub mod capture;
+pub mod clipboard_content;
+pub mod clipboard_poller;
+pub mod clipboard_watch;
+pub mod copy_timing;
+pub mod gesture;
+pub mod history;
+pub mod redact;
+pub mod rules;
+pub mod server;
+
+#[cfg(windows)]
+pub mod clipboard_snapshot;
+
+#[cfg(windows)]
+pub mod global_hook_simulator;
+
+#[cfg(windows)]
+pub mod ui_automation_improved;
+
+#[cfg(target_os = "linux")]
+pub mod atspi;
+
+#[cfg(target_os = "linux")]
+pub mod primary_selection;
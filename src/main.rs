@@ -1,555 +1,448 @@
 // 禁用在 Windows 上运行时弹出的控制台窗口
 // #![windows_subsystem = "windows"]
 
-use std::io;
-
-// --- 方法一：轮询剪贴板 ---
-// 这是最简单、最稳定的方法。
-mod clipboard_poller {
-    use arboard::Clipboard;
-    use std::{thread, time::Duration};
-
-    pub fn run() {
-        println!("方法一：剪贴板轮询模式已启动。");
-        println!("请在任何地方复制文本 (Ctrl+C)，这里会显示出来。按 Ctrl+C 退出此程序。");
-
-        let mut clipboard = Clipboard::new().expect("无法初始化剪贴板");
-        let mut previous_text = clipboard.get_text().unwrap_or_default();
-
-        loop {
-            let current_text = clipboard.get_text().unwrap_or_default();
-            if !current_text.is_empty() && current_text != previous_text {
-                println!("\n--- [剪贴板更新] ---");
-                println!("{}", current_text);
-                println!("--- [内容结束] ---\n");
-                previous_text = current_text;
-            }
-            thread::sleep(Duration::from_millis(500)); // 每 500 毫秒检查一次
-        }
-    }
+use std::{
+    fs,
+    io::{self, Write},
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use clap::{Args, Parser, Subcommand};
+use text_listener::{
+    capture::{self, CaptureKind, CaptureMode, ConsoleOptions, OutputFormat},
+    clipboard_content::MIME_TEXT,
+    clipboard_poller::{self, ChangeDetection, ClipboardPoller},
+    history::{HistoryStore, SearchHit, SearchQuery, SourceStats},
+    redact::Redactor,
+    rules::{self, Action, RuleError, RuleSet},
+};
+#[cfg(windows)]
+use text_listener::{
+    clipboard_watch::WaitStrategy,
+    global_hook_simulator::{self, GlobalHookSource},
+    ui_automation_improved::{self, UiAutomationSource},
+};
+#[cfg(target_os = "linux")]
+use text_listener::{
+    atspi::{self, AtspiSource},
+    primary_selection::{self, PrimarySelectionSource},
+};
+
+/// 捕获用户在其他程序中复制或选中的文本
+#[derive(Parser)]
+#[command(name = "text_listener", version)]
+struct Cli {
+    /// 不带子命令时进入交互式菜单
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-// --- 方法三：全局鼠标钩子 + 模拟按键 ---
-// 这是一个"黑科技"方法，有侵入性，并且需要 unsafe 代码。
-// 它会监听鼠标左键的抬起，然后模拟 Ctrl+C，再从剪贴板读取。
-mod global_hook_simulator {
-    use arboard::Clipboard;
-    use std::{sync::atomic::{AtomicBool, Ordering}, thread, time::{Duration, Instant}};
-    use windows::Win32::{
-        Foundation::{LPARAM, LRESULT, WPARAM},
-        System::Threading::GetCurrentThreadId,
-        UI::{
-            Input::KeyboardAndMouse::{
-                SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, VK_C, VK_LCONTROL,
-                VK_ESCAPE,
-            },
-            WindowsAndMessaging::{
-                CallNextHookEx, GetMessageW, SetWindowsHookExW, UnhookWindowsHookEx, HHOOK, MSG,
-                WH_MOUSE_LL, WM_LBUTTONUP, PostThreadMessageW, WM_USER, TranslateMessage, DispatchMessageW,
-                WM_QUIT, WM_KEYDOWN, WH_KEYBOARD_LL, KBDLLHOOKSTRUCT,
-            },
-        },
-        System::Console::{SetConsoleCtrlHandler, CTRL_C_EVENT, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT},
-    };
+#[derive(Subcommand)]
+enum Command {
+    /// 监听剪贴板（方法一）
+    Poll {
+        /// 轮询间隔（毫秒）；使用系统通知时也用于合并重复通知
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+        /// 不使用系统通知，始终定时轮询
+        #[arg(long)]
+        force_polling: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// UI Automation 模式（方法二）；Linux 上使用 AT-SPI
+    Uia {
+        /// 没有选中文本时输出焦点输入框的全部内容（密码框除外）
+        #[arg(long)]
+        field_values: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// 全局鼠标钩子模式（方法三，仅 Windows），按 ESC 退出
+    Hook {
+        /// 模拟复制后最多等待目标程序多久（毫秒）
+        #[arg(long, default_value_t = 500)]
+        max_latency: u64,
+        /// 检查剪贴板的初始间隔（毫秒），之后逐次翻倍
+        #[arg(long, default_value_t = 5)]
+        poll_interval: u64,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// 监听 PRIMARY selection（方法四，仅 Linux）
+    Primary {
+        /// 选区稳定多久后才读取（毫秒）
+        #[arg(long, default_value_t = 300)]
+        settle: u64,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// 查看最近的捕获历史
+    History {
+        /// 显示的条数
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// 只显示指定模式的捕获：clipboard、uia、hook、primary
+        #[arg(long, value_parser = parse_mode)]
+        mode: Option<CaptureMode>,
+        /// 只显示来自指定程序的捕获，比如 chrome.exe
+        #[arg(long)]
+        process: Option<String>,
+        /// 输出格式：text 或 jsonl
+        #[arg(long, default_value = "text", value_parser = parse_format)]
+        format: OutputFormat,
+    },
+    /// 搜索捕获历史
+    Search(SearchArgs),
+    /// 按来源程序统计捕获历史
+    Sources,
+    /// 导出一条历史记录的原始内容（HTML 或 RTF 源码、PNG 图片、文件列表或纯文本）
+    Export {
+        /// 记录 id，见 history 或 search 的输出
+        id: i64,
+        /// 写入的文件，默认写到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 显示生效的捕获规则
+    Rules {
+        /// 规则文件，默认见 `rules::default_path`
+        #[arg(long)]
+        rules: Option<PathBuf>,
+    },
+    /// 交互式菜单
+    Menu,
+}
 
-    // 全局变量来存储钩子句柄和状态
-    static mut MOUSE_HOOK: Option<HHOOK> = None;
-    static mut KEYBOARD_HOOK: Option<HHOOK> = None;
-    static mut LAST_CLICK_TIME: Option<Instant> = None;
-    static mut MAIN_THREAD_ID: u32 = 0;
-    static SHOULD_EXIT: AtomicBool = AtomicBool::new(false);
-    static IS_SIMULATING_CTRL_C: AtomicBool = AtomicBool::new(false);
-    
-    // 控制台信号处理函数
-    unsafe extern "system" fn console_ctrl_handler(ctrl_type: u32) -> windows::Win32::Foundation::BOOL {
-        match ctrl_type {
-            CTRL_C_EVENT | CTRL_BREAK_EVENT => {
-                // 只有在程序模拟 Ctrl+C 时才拦截信号，否则让用户正常操作通过
-                if IS_SIMULATING_CTRL_C.load(Ordering::Relaxed) {
-                    println!("[调试] 拦截了程序模拟的 Ctrl+C 信号，防止程序退出");
-                    windows::Win32::Foundation::BOOL::from(true) // 返回 TRUE 表示已处理该信号
-                } else {
-                    println!("[事件] 检测到用户的 Ctrl+C 操作，正在优雅退出...");
-                    SHOULD_EXIT.store(true, Ordering::Relaxed);
-                    // 发送退出消息到主线程
-                    let _ = PostThreadMessageW(MAIN_THREAD_ID, WM_QUIT, WPARAM(0), LPARAM(0));
-                    windows::Win32::Foundation::BOOL::from(true) // 返回 TRUE 表示我们已经处理了这个信号
-                }
-            }
-            CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => {
-                println!("[事件] 检测到系统关闭信号，正在清理资源...");
-                SHOULD_EXIT.store(true, Ordering::Relaxed);
-                // 给程序一点时间来清理资源
-                thread::sleep(Duration::from_millis(100));
-                windows::Win32::Foundation::BOOL::from(true)
+// 各捕获模式共用的输出参数
+#[derive(Args)]
+struct OutputArgs {
+    /// 不把捕获写入历史数据库
+    #[arg(long)]
+    no_history: bool,
+    /// 输出格式：text 或 jsonl（每个捕获一行 JSON，状态信息只写到标准错误）
+    #[arg(long, default_value = "text", value_parser = parse_format)]
+    format: OutputFormat,
+    /// 捕获规则文件，默认读取配置目录下的 text_listener/rules.toml
+    #[arg(long)]
+    rules: Option<PathBuf>,
+    /// 脱敏配置文件，默认读取配置目录下的 text_listener/redact.toml
+    #[arg(long, conflicts_with = "no_redact")]
+    redact: Option<PathBuf>,
+    /// 不做脱敏处理，原样输出和保存捕获内容
+    #[arg(long)]
+    no_redact: bool,
+    /// 启动本地接口，推送捕获并提供历史查询和暂停/恢复：端口号，或者 127.0.0.1:端口
+    #[arg(long, value_name = "ADDR", value_parser = parse_serve_addr)]
+    serve: Option<SocketAddr>,
+}
+
+impl OutputArgs {
+    // 读取规则和脱敏配置。配置有误时直接退出，免得用户以为配置已经生效
+    fn prepare(&self) -> Result<(RuleSet, ConsoleOptions), i32> {
+        let fail = |e: &dyn std::fmt::Display| {
+            eprintln!("[错误] {}", e);
+            2
+        };
+        let rules = load_rules(self.rules.as_deref()).map_err(|e| fail(&e))?;
+        let redactor = if self.no_redact {
+            Redactor::disabled()
+        } else {
+            match &self.redact {
+                Some(path) => Redactor::load(path),
+                None => Redactor::load_default(),
             }
-            _ => windows::Win32::Foundation::BOOL::from(false), // 其他信号交给默认处理器
-        }
+            .map_err(|e| fail(&e))?
+        };
+        let options = ConsoleOptions {
+            save_history: !self.no_history,
+            format: self.format,
+            redactor,
+            serve: self.serve,
+        };
+        Ok((rules, options))
     }
+}
 
-    // 模拟按下和释放 Ctrl+C
-    fn simulate_ctrl_c() {
-        // 设置标志，表示程序正在模拟 Ctrl+C
-        IS_SIMULATING_CTRL_C.store(true, Ordering::Relaxed);
-        
-        // 需要 unsafe 因为我们在调用系统 API
-        unsafe {
-            let inputs = &mut [
-                // Press LCtrl
-                INPUT {
-                    r#type: INPUT_KEYBOARD,
-                    Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                        ki: KEYBDINPUT {
-                            wVk: VK_LCONTROL,
-                            ..Default::default()
-                        },
-                    },
-                },
-                // Press C
-                INPUT {
-                    r#type: INPUT_KEYBOARD,
-                    Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                        ki: KEYBDINPUT {
-                            wVk: VK_C,
-                            ..Default::default()
-                        },
-                    },
-                },
-                // Release C
-                INPUT {
-                    r#type: INPUT_KEYBOARD,
-                    Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                        ki: KEYBDINPUT {
-                            wVk: VK_C,
-                            dwFlags: KEYEVENTF_KEYUP,
-                            ..Default::default()
-                        },
-                    },
-                },
-                // Release LCtrl
-                INPUT {
-                    r#type: INPUT_KEYBOARD,
-                    Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                        ki: KEYBDINPUT {
-                            wVk: VK_LCONTROL,
-                            dwFlags: KEYEVENTF_KEYUP,
-                            ..Default::default()
-                        },
-                    },
-                },
-            ];
-            SendInput(inputs, std::mem::size_of::<INPUT>() as i32);
-        }
-        
-        // 短暂延迟后清除标志，确保信号处理器有时间处理
-        thread::sleep(Duration::from_millis(50));
-        IS_SIMULATING_CTRL_C.store(false, Ordering::Relaxed);
+fn load_rules(path: Option<&Path>) -> Result<RuleSet, RuleError> {
+    match path {
+        Some(path) => RuleSet::load(path),
+        None => RuleSet::load_default(),
     }
+}
 
-    // 键盘钩子的回调函数
-    unsafe extern "system" fn low_level_keyboard_proc(
-        n_code: i32,
-        w_param: WPARAM,
-        l_param: LPARAM,
-    ) -> LRESULT {
-        if n_code >= 0 && w_param.0 as u32 == WM_KEYDOWN {
-             let kbd_struct = *(l_param.0 as *const KBDLLHOOKSTRUCT);
-             if kbd_struct.vkCode == VK_ESCAPE.0 as u32 {
-                println!("[事件] 检测到 ESC 键，准备退出...");
-                SHOULD_EXIT.store(true, Ordering::Relaxed);
-                let _ = PostThreadMessageW(MAIN_THREAD_ID, WM_QUIT, WPARAM(0), LPARAM(0));
-                return LRESULT(1); // 阻止 ESC 键传递给其他应用
-            }
+#[derive(Args)]
+struct SearchArgs {
+    /// 关键词，结果必须包含全部关键词
+    keywords: Vec<String>,
+    /// 起始时间：2024-05-01、"2024-05-01 13:30"，或相对时间 30m、2h、7d（表示多久之前）
+    #[arg(long, value_parser = parse_time)]
+    since: Option<SystemTime>,
+    /// 截止时间，格式同 --since
+    #[arg(long, value_parser = parse_time)]
+    until: Option<SystemTime>,
+    /// 捕获模式：clipboard、uia、hook、primary
+    #[arg(long, value_parser = parse_mode)]
+    mode: Option<CaptureMode>,
+    /// 来源窗口标题包含的文本
+    #[arg(long)]
+    window: Option<String>,
+    /// 来源程序的可执行文件名，比如 chrome.exe
+    #[arg(long)]
+    process: Option<String>,
+    /// 最多返回的条数
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+    /// 输出格式：text 或 jsonl
+    #[arg(long, default_value = "text", value_parser = parse_format)]
+    format: OutputFormat,
+}
+
+impl SearchArgs {
+    fn to_query(&self) -> SearchQuery {
+        SearchQuery {
+            keywords: self.keywords.clone(),
+            since: self.since,
+            until: self.until,
+            mode: self.mode,
+            window: self.window.clone(),
+            process: self.process.clone(),
+            limit: self.limit,
+            ..SearchQuery::default()
         }
-        CallNextHookEx(KEYBOARD_HOOK.unwrap(), n_code, w_param, l_param)
     }
+}
 
-    // 鼠标钩子的回调函数
-    // 这个函数会在每次鼠标事件发生时被 Windows 调用
-    unsafe extern "system" fn low_level_mouse_proc(
-        n_code: i32,
-        w_param: WPARAM,
-        l_param: LPARAM,
-    ) -> LRESULT {
-        if n_code >= 0 {
-            // 当鼠标左键抬起时
-            if w_param.0 as u32 == WM_LBUTTONUP {
-                let now = Instant::now();
-                
-                // 防抖动：如果距离上次点击时间太短，则忽略
-                if let Some(last_time) = LAST_CLICK_TIME {
-                    if now.duration_since(last_time) < Duration::from_millis(300) {
-                        return CallNextHookEx(MOUSE_HOOK.unwrap(), n_code, w_param, l_param);
-                    }
-                }
-                LAST_CLICK_TIME = Some(now);
-                
-                println!("[事件] 检测到鼠标左键抬起。");
-                
-                // 不在钩子回调中执行耗时操作，而是发送消息到主线程处理
-                let _ = PostThreadMessageW(MAIN_THREAD_ID, WM_USER + 1, WPARAM(0), LPARAM(0));
-            }
+fn main() {
+    let cli = Cli::parse();
+    let code = match cli.command.unwrap_or(Command::Menu) {
+        Command::Menu => {
+            run_menu();
+            0
         }
-        // 把事件传递给下一个钩子，否则整个系统会卡住！
-        CallNextHookEx(MOUSE_HOOK.unwrap(), n_code, w_param, l_param)
-    }
-    
-    // 处理文本捕获的函数，在主线程中执行
-    fn handle_text_capture() {
-        match Clipboard::new() {
-            Ok(mut clipboard) => {
-                // 1. 保存用户当前的剪贴板内容
-                let user_clipboard_backup = clipboard.get_text().ok();
-                println!("[操作] 已备份用户剪贴板内容");
-                
-                // 2. 模拟 Ctrl+C
-                println!("[操作] 正在模拟 Ctrl+C...");
-                simulate_ctrl_c();
-
-                // 3. 等待一小段时间，让目标应用有时间把文本放到剪贴板
-                thread::sleep(Duration::from_millis(150));
-
-                // 4. 从剪贴板读取捕获的内容
-                match clipboard.get_text() {
-                    Ok(captured_text) => {
-                        if !captured_text.is_empty() && captured_text.trim().len() > 0 {
-                            // 检查是否与用户备份的内容相同，避免显示用户自己的内容
-                            let is_same_as_backup = user_clipboard_backup
-                                .as_ref()
-                                .map(|backup| backup == &captured_text)
-                                .unwrap_or(false);
-                            
-                            if !is_same_as_backup {
-                                println!("\n--- [自动捕获内容] ---");
-                                println!("{}", captured_text);
-                                println!("--- [内容结束] ---\n");
-                            } else {
-                                println!("[结果] 检测到的内容与用户剪贴板相同，可能没有新的选中文本。");
-                            }
-                        } else {
-                            println!("[结果] 剪贴板为空或只包含空白字符，可能没有选中文本。");
-                        }
-                    }
-                    Err(e) => println!("[错误] 读取剪贴板失败: {:?}", e),
-                }
-                
-                // 5. 恢复用户的剪贴板内容
-                if let Some(backup_content) = user_clipboard_backup {
-                    if let Err(e) = clipboard.set_text(backup_content) {
-                        println!("[警告] 恢复用户剪贴板内容失败: {:?}", e);
+        Command::Poll {
+            interval,
+            force_polling,
+            output,
+        } => match output.prepare() {
+            Ok((rules, options)) => {
+                eprintln!("[状态] 剪贴板监听已启动，按 Ctrl+C 退出。");
+                let source = ClipboardPoller {
+                    interval: Duration::from_millis(interval),
+                    detection: if force_polling {
+                        ChangeDetection::Polling
                     } else {
-                        println!("[操作] 已恢复用户剪贴板内容");
-                    }
-                } else {
-                    // 如果用户原本剪贴板为空，清空剪贴板
-                    let _ = clipboard.set_text("".to_string());
-                    println!("[操作] 已清空剪贴板（用户原本为空）");
-                }
-            }
-            Err(e) => println!("[错误] 无法初始化剪贴板: {:?}", e),
-        }
-    }
-
-    pub fn run() {
-        println!("方法三：全局鼠标钩子模式已启动。");
-        println!("请在任何地方用鼠标选中一段文本，然后松开左键。");
-        println!("✅ 改进：程序会自动备份和恢复你的剪贴板内容，不影响正常使用");
-        println!("提示：程序会自动过滤重复点击，300ms内的连续点击会被忽略。");
-        println!("退出方式：按 ESC 键退出，或关闭此控制台窗口");
-        
-        // 重置退出标志
-        SHOULD_EXIT.store(false, Ordering::Relaxed);
-
-        // 需要 unsafe 因为我们在设置一个全局钩子
-        unsafe {
-            // 设置控制台信号处理器，防止模拟的 Ctrl+C 导致程序退出
-            if let Err(e) = SetConsoleCtrlHandler(Some(console_ctrl_handler), true) {
-                println!("[警告] 设置控制台信号处理器失败: {:?}", e);
-            } else {
-                println!("[状态] 控制台信号处理器已设置，程序不会因模拟 Ctrl+C 而退出。");
+                        ChangeDetection::Auto
+                    },
+                    rules,
+                };
+                capture::run_to_console(source, &options);
+                0
             }
-            // 获取当前线程ID
-            MAIN_THREAD_ID = GetCurrentThreadId();
-            
-            // 设置键盘钩子用于检测 ESC 键
-            let keyboard_hook = match SetWindowsHookExW(
-                WH_KEYBOARD_LL,
-                Some(low_level_keyboard_proc),
-                None,
-                0,
-            ) {
-                Ok(h) => h,
-                Err(e) => {
-                    println!("[错误] 设置键盘钩子失败: {:?}", e);
-                    return;
-                }
-            };
-            KEYBOARD_HOOK = Some(keyboard_hook);
-            
-            // 设置一个低级鼠标钩子
-            let mouse_hook = match SetWindowsHookExW(
-                WH_MOUSE_LL,
-                Some(low_level_mouse_proc),
-                None, // hmod: None 表示钩子与任何特定模块无关
-                0,    // dwThreadId: 0 表示这是一个全局钩子
-            ) {
-                Ok(h) => h,
-                Err(e) => {
-                    println!("[错误] 设置鼠标钩子失败: {:?}", e);
-                    // 如果鼠标钩子失败，也要清理键盘钩子
-                    let _ = UnhookWindowsHookEx(keyboard_hook);
-                    return;
-                }
+            Err(code) => code,
+        },
+        Command::Uia { field_values, output } => run_uia(field_values, &output),
+        Command::Hook {
+            max_latency,
+            poll_interval,
+            output,
+        } => run_hook(max_latency, poll_interval, &output),
+        Command::Primary { settle, output } => run_primary(settle, &output),
+        Command::History {
+            limit,
+            mode,
+            process,
+            format,
+        } => {
+            let query = SearchQuery {
+                mode,
+                process,
+                limit,
+                ..SearchQuery::default()
             };
-            MOUSE_HOOK = Some(mouse_hook);
-
-            println!("[状态] 鼠标和键盘钩子已成功安装，开始监听...");
-
-            // 运行一个消息循环，这是接收钩子事件所必需的
-            println!("[状态] 钩子已激活，开始持续监听鼠标事件...");
-            println!("[提示] 现在可以在任何地方选中文本并松开鼠标左键进行捕获。");
-            
-            let mut msg: MSG = Default::default();
-            loop {
-                // 检查是否需要退出
-                if SHOULD_EXIT.load(Ordering::Relaxed) {
-                    println!("[状态] 检测到退出信号，正在停止监听...");
-                    break;
+            match run_search(&query) {
+                Ok(hits) => {
+                    match format {
+                        OutputFormat::Text => print_history(&hits),
+                        // 与文本输出一样按时间顺序
+                        OutputFormat::Jsonl => hits.iter().rev().for_each(|hit| println!("{}", hit.entry.to_json())),
+                    }
+                    0
                 }
-                
-                let result = GetMessageW(&mut msg, None, 0, 0);
-                
-                // 检查是否收到退出消息
-                if !result.as_bool() || msg.message == WM_QUIT {
-                    println!("[状态] 收到系统退出信号，正在停止监听...");
-                    break;
+                Err(e) => {
+                    eprintln!("[错误] 读取历史失败: {}", e);
+                    1
                 }
-                
-                // 检查是否是我们的自定义消息
-                if msg.message == WM_USER + 1 {
-                    handle_text_capture();
-                } else {
-                    // 处理其他消息
-                    TranslateMessage(&msg);
-                    DispatchMessageW(&msg);
+            }
+        }
+        Command::Search(args) => match run_search(&args.to_query()) {
+            Ok(hits) => {
+                match args.format {
+                    OutputFormat::Text => print_hits(&hits),
+                    OutputFormat::Jsonl => print_hits_json(&hits),
                 }
+                0
             }
-
-            // 程序退出前，卸载钩子
-            if let Err(e) = UnhookWindowsHookEx(mouse_hook) {
-                println!("[警告] 卸载鼠标钩子时出错: {:?}", e);
-            } else {
-                println!("[状态] 鼠标钩子已成功卸载。");
+            Err(e) => {
+                eprintln!("[错误] 搜索历史失败: {}", e);
+                1
             }
-            
-            if let Err(e) = UnhookWindowsHookEx(keyboard_hook) {
-                println!("[警告] 卸载键盘钩子时出错: {:?}", e);
-            } else {
-                println!("[状态] 键盘钩子已成功卸载。");
+        },
+        Command::Sources => match HistoryStore::open_default().and_then(|store| store.sources()) {
+            Ok(sources) => {
+                print_sources(&sources);
+                0
+            }
+            Err(e) => {
+                eprintln!("[错误] 读取历史失败: {}", e);
+                1
             }
-        }
-    }
-}
-
-// --- 方法二：Windows UI Automation ---
-// 这是最“正确”但也是最复杂的方法。
-// 由于其极端复杂性，提供一个完整的、健壮的示例非常困难。
-// 下面的代码是一个“概念验证”，展示了其基本思路，但省略了大量的错误处理和复杂的逻辑。
-mod ui_automation_improved {
-    use std::{thread, time::Duration, sync::atomic::{AtomicBool, Ordering}};
-    use windows::{
-        core::{ComInterface, HSTRING},
-        Win32::{
-            System::Com::{
-                CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
-                COINIT_MULTITHREADED,
-            },
-            UI::Accessibility::{
-                CUIAutomation, IUIAutomation, IUIAutomationTextPattern, UIA_TextPatternId,
-                IUIAutomationElement, UIA_ValuePatternId, IUIAutomationValuePattern,
-                UIA_EditControlTypeId, UIA_DocumentControlTypeId, UIA_TextControlTypeId,
-            },
-            Foundation::{HWND, POINT},
-            UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowTextW, GetCursorPos, WindowFromPoint},
         },
-    };
-
-    static SHOULD_EXIT: AtomicBool = AtomicBool::new(false);
-
-    // 尝试从元素获取选中的文本
-    unsafe fn try_get_selected_text(element: &IUIAutomationElement) -> Option<String> {
-        // 方法1: 尝试 TextPattern
-        if let Ok(pattern_unknown) = element.GetCurrentPattern(UIA_TextPatternId) {
-            if let Ok(text_pattern) = pattern_unknown.cast::<IUIAutomationTextPattern>() {
-                if let Ok(selection) = text_pattern.GetSelection() {
-                    let selection_len = selection.Length().unwrap_or(0);
-                    if selection_len > 0 {
-                        for i in 0..selection_len {
-                            if let Ok(range) = selection.GetElement(i) {
-                                if let Ok(text) = range.GetText(-1) {
-                                    let text_str = text.to_string();
-                                    if !text_str.trim().is_empty() {
-                                        return Some(text_str);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        Command::Export { id, output } => match export(id, output.as_deref()) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("[错误] 导出失败: {}", e);
+                1
             }
-        }
-
-        // 方法2: 尝试 ValuePattern (适用于输入框)
-        if let Ok(pattern_unknown) = element.GetCurrentPattern(UIA_ValuePatternId) {
-            if let Ok(value_pattern) = pattern_unknown.cast::<IUIAutomationValuePattern>() {
-                if let Ok(value) = value_pattern.CurrentValue() {
-                    let value_str = value.to_string();
-                    if !value_str.trim().is_empty() {
-                        return Some(format!("[输入框内容] {}", value_str));
-                    }
-                }
+        },
+        Command::Rules { rules } => match load_rules(rules.as_deref()) {
+            Ok(set) => {
+                print_rules(rules.as_deref(), &set);
+                0
             }
-        }
-
-        None
-    }
+            Err(e) => {
+                eprintln!("[错误] {}", e);
+                2
+            }
+        },
+    };
+    process::exit(code);
+}
 
-    // 检查元素是否是文本相关的控件
-    unsafe fn is_text_element(element: &IUIAutomationElement) -> bool {
-        if let Ok(control_type) = element.CurrentControlType() {
-            let type_id = control_type.0;
-            type_id == UIA_EditControlTypeId.0 || 
-            type_id == UIA_DocumentControlTypeId.0 || 
-            type_id == UIA_TextControlTypeId.0
-        } else {
-            false
-        }
+fn print_rules(path: Option<&Path>, set: &RuleSet) {
+    let path = path.map_or_else(rules::default_path, Path::to_path_buf);
+    let state = if path.exists() { "" } else { "（不存在，只使用内置规则）" };
+    println!("规则文件: {}{}", path.display(), state);
+    for (index, rule) in set.rules().iter().enumerate() {
+        println!("{:>3}. {}", index + 1, rule);
     }
+    let default = match set.default_action() {
+        Action::Allow => "allow",
+        Action::Deny => "deny",
+    };
+    println!("没有规则命中时: {}", default);
+}
 
-    // 获取窗口信息
-    unsafe fn get_window_info(hwnd: HWND) -> String {
-        let mut buffer = [0u16; 256];
-        let len = GetWindowTextW(hwnd, &mut buffer);
-        if len > 0 {
-            String::from_utf16_lossy(&buffer[..len as usize])
-        } else {
-            "未知窗口".to_string()
+// 把记录的原始数据写到文件或标准输出；纯文本记录导出文本本身
+fn export(id: i64, output: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let store = HistoryStore::open_default()?;
+    let entry = store.get(id)?.ok_or_else(|| format!("没有 id 为 {} 的记录", id))?;
+    let data = store.data(id)?.unwrap_or_else(|| entry.text.into_bytes());
+    match output {
+        Some(path) => {
+            fs::write(path, &data)?;
+            let kind = match entry.kind {
+                CaptureKind::FieldValue => "输入框内容，",
+                CaptureKind::Selection => "",
+            };
+            eprintln!("[状态] 已导出 {}（{}{}，{} 字节）。", path.display(), kind, entry.mime_type, data.len());
         }
+        None => io::stdout().write_all(&data)?,
     }
+    Ok(())
+}
 
-    pub fn run() {
-        println!("方法二：改进的 UI Automation 模式已启动。");
-        println!("这个版本会持续监听焦点变化和文本选择。");
-        println!("支持多种控件类型：编辑框、文档、富文本等。");
-        println!("退出方式：按 Ctrl+C 退出程序");
-        println!("\n[提示] 请在不同的应用中选择文本，程序会自动检测...");
-
-        SHOULD_EXIT.store(false, Ordering::Relaxed);
-
-        unsafe {
-            if let Err(e) = CoInitializeEx(None, COINIT_MULTITHREADED) {
-                println!("[错误] COM 初始化失败: {:?}", e);
-                return;
-            }
-
-            let automation: IUIAutomation = match CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER) {
-                Ok(inst) => inst,
-                Err(e) => {
-                    println!("[错误] 创建 UI Automation 实例失败: {:?}", e);
-                    CoUninitialize();
-                    return;
-                }
-            };
-
-            println!("[状态] UI Automation 已初始化，开始监听...");
-
-            let mut last_window: Option<HWND> = None;
-            let mut last_text = String::new();
-            let mut check_count = 0;
-
-            loop {
-                if SHOULD_EXIT.load(Ordering::Relaxed) {
-                    break;
-                }
+#[cfg(windows)]
+fn run_uia(field_values: bool, output: &OutputArgs) -> i32 {
+    let (rules, options) = match output.prepare() {
+        Ok(prepared) => prepared,
+        Err(code) => return code,
+    };
+    eprintln!("[状态] UI Automation 模式已启动，按 Ctrl+C 退出。");
+    capture::run_to_console(UiAutomationSource { rules, field_values }, &options);
+    0
+}
 
-                check_count += 1;
-                if check_count % 20 == 0 { // 每10秒显示一次状态
-                    println!("[状态] 持续监听中... (已检查 {} 次)", check_count);
-                }
+#[cfg(windows)]
+fn run_hook(max_latency: u64, poll_interval: u64, output: &OutputArgs) -> i32 {
+    let (rules, options) = match output.prepare() {
+        Ok(prepared) => prepared,
+        Err(code) => return code,
+    };
+    eprintln!("[状态] 全局鼠标钩子模式已启动，按 ESC 退出。");
+    let defaults = WaitStrategy::default();
+    let source = GlobalHookSource {
+        wait: WaitStrategy {
+            initial_interval: Duration::from_millis(poll_interval.max(1)),
+            max_interval: defaults.max_interval.max(Duration::from_millis(poll_interval)),
+            max_latency: Duration::from_millis(max_latency),
+            ..defaults
+        },
+        rules,
+    };
+    capture::run_to_console(source, &options);
+    0
+}
 
-                // 获取当前前台窗口
-                let current_window = GetForegroundWindow();
-                if current_window.0 == 0 {
-                    thread::sleep(Duration::from_millis(500));
-                    continue;
-                }
+#[cfg(target_os = "linux")]
+fn run_uia(field_values: bool, output: &OutputArgs) -> i32 {
+    let (rules, options) = match output.prepare() {
+        Ok(prepared) => prepared,
+        Err(code) => return code,
+    };
+    eprintln!("[状态] AT-SPI 模式已启动，按 Ctrl+C 退出。");
+    capture::run_to_console(AtspiSource { rules, field_values }, &options);
+    0
+}
 
-                // 检查窗口是否变化
-                let window_changed = last_window.map_or(true, |last| last != current_window);
-                if window_changed {
-                    let window_title = get_window_info(current_window);
-                    println!("[事件] 窗口切换到: {}", window_title);
-                    last_window = Some(current_window);
-                }
+#[cfg(not(any(windows, target_os = "linux")))]
+fn run_uia(_: bool, _: &OutputArgs) -> i32 {
+    eprintln!("[错误] 该模式依赖 Windows API，当前平台不支持。");
+    2
+}
 
-                // 尝试获取焦点元素
-                match automation.GetFocusedElement() {
-                    Ok(focused_element) => {
-                        // 检查是否是文本相关元素
-                        if is_text_element(&focused_element) {
-                            if let Some(selected_text) = try_get_selected_text(&focused_element) {
-                                // 避免重复显示相同内容
-                                if selected_text != last_text && selected_text.len() > 2 {
-                                    println!("\n--- [UIA 捕获内容] ---");
-                                    println!("{}", selected_text);
-                                    println!("--- [内容结束] ---\n");
-                                    last_text = selected_text;
-                                }
-                            }
-                        }
-
-                        // 也尝试获取鼠标位置的元素
-                        let mut cursor_pos = POINT { x: 0, y: 0 };
-                        if GetCursorPos(&mut cursor_pos).is_ok() {
-                            let hwnd_under_cursor = WindowFromPoint(cursor_pos);
-                            if hwnd_under_cursor.0 != 0 && hwnd_under_cursor != current_window {
-                                if let Ok(element_under_cursor) = automation.ElementFromHandle(hwnd_under_cursor) {
-                                    if is_text_element(&element_under_cursor) {
-                                        if let Some(text) = try_get_selected_text(&element_under_cursor) {
-                                            if text != last_text && text.len() > 2 {
-                                                println!("\n--- [鼠标位置文本] ---");
-                                                println!("{}", text);
-                                                println!("--- [内容结束] ---\n");
-                                                last_text = text;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        // 焦点元素获取失败，这很常见，不需要报错
-                    }
-                }
+#[cfg(not(windows))]
+fn run_hook(_: u64, _: u64, _: &OutputArgs) -> i32 {
+    eprintln!("[错误] 该模式依赖 Windows API，当前平台不支持。");
+    2
+}
 
-                thread::sleep(Duration::from_millis(500)); // 每500ms检查一次
-            }
+#[cfg(target_os = "linux")]
+fn run_primary(settle: u64, output: &OutputArgs) -> i32 {
+    let (rules, options) = match output.prepare() {
+        Ok(prepared) => prepared,
+        Err(code) => return code,
+    };
+    eprintln!("[状态] 选中文本监听已启动，按 Ctrl+C 退出。");
+    let source = PrimarySelectionSource {
+        settle: Duration::from_millis(settle),
+        rules,
+    };
+    capture::run_to_console(source, &options);
+    0
+}
 
-            println!("[状态] UI Automation 监听已停止。");
-            CoUninitialize();
-        }
-    }
+#[cfg(not(target_os = "linux"))]
+fn run_primary(_: u64, _: &OutputArgs) -> i32 {
+    eprintln!("[错误] 该模式依赖 X11/Wayland，当前平台不支持。");
+    2
 }
 
-fn main() {
+fn run_menu() {
     loop {
         println!("\n请选择要运行的 Demo 模式:");
-        println!("1. 剪贴板轮询 (最稳定，推荐)");
-        println!("2. UI Automation (最复杂，概念演示)");
+        println!("1. 剪贴板监听 (最稳定，推荐)");
+        println!("2. UI Automation (最复杂，概念演示；Linux 上使用 AT-SPI)");
         println!("3. 全局鼠标钩子 (有风险，侵入式)");
+        println!("4. 选中文本 (仅 Linux X11/Wayland，无需复制)");
+        println!("h. 查看最近的捕获历史");
+        println!("s. 搜索捕获历史");
         println!("q. 退出");
-        print!("请输入选项 (1, 2, 3, q): ");
+        print!("请输入选项 (1, 2, 3, 4, h, s, q): ");
 
         io::Write::flush(&mut io::stdout()).unwrap();
 
@@ -558,8 +451,22 @@ fn main() {
 
         match choice.trim() {
             "1" => clipboard_poller::run(),
+            #[cfg(windows)]
             "2" => ui_automation_improved::run(),
+            #[cfg(windows)]
             "3" => global_hook_simulator::run(),
+            #[cfg(target_os = "linux")]
+            "2" => atspi::run(),
+            #[cfg(not(any(windows, target_os = "linux")))]
+            "2" => println!("该模式依赖 Windows API 或 AT-SPI，当前平台不支持。"),
+            #[cfg(not(windows))]
+            "3" => println!("该模式依赖 Windows API，当前平台不支持。"),
+            #[cfg(target_os = "linux")]
+            "4" => primary_selection::run(),
+            #[cfg(not(target_os = "linux"))]
+            "4" => println!("该模式依赖 X11/Wayland，当前平台不支持。"),
+            "h" | "H" => show_history(20),
+            "s" | "S" => search_interactive(),
             "q" | "Q" => {
                 println!("程序退出。");
                 break;
@@ -568,3 +475,202 @@ fn main() {
         }
     }
 }
+
+// 打印最近的 `limit` 条捕获历史
+fn show_history(limit: usize) {
+    let query = SearchQuery {
+        limit,
+        ..SearchQuery::default()
+    };
+    match run_search(&query) {
+        Ok(hits) => print_history(&hits),
+        Err(e) => println!("[错误] 读取历史失败: {}", e),
+    }
+}
+
+// 按时间顺序打印历史记录（最旧的在前，最新的紧挨着提示符）
+fn print_history(hits: &[SearchHit]) {
+    if hits.is_empty() {
+        println!("历史记录为空。");
+        return;
+    }
+
+    println!("\n--- [最近 {} 条捕获] ---", hits.len());
+    for hit in hits.iter().rev() {
+        print_entry_header(hit);
+        println!("{}", hit.entry.text);
+    }
+    println!("--- [历史结束] ---\n");
+}
+
+fn print_entry_header(hit: &SearchHit) {
+    let time: DateTime<Local> = hit.entry.timestamp.into();
+    let window = hit.entry.window_title.as_deref().unwrap_or("-");
+    let process = hit.entry.process.as_deref().map(|p| format!(" ({})", p)).unwrap_or_default();
+    println!(
+        "#{} {} [{}] {}{}",
+        hit.entry.id,
+        time.format("%Y-%m-%d %H:%M:%S"),
+        hit.entry.mode.banner(),
+        window,
+        process
+    );
+    if let Some(url) = &hit.entry.url {
+        println!("[网址] {}", url);
+    }
+    if hit.entry.mime_type != MIME_TEXT {
+        println!("[格式] {}（可用 export {} 导出原始内容）", hit.entry.mime_type, hit.entry.id);
+    }
+    if hit.entry.kind == CaptureKind::FieldValue {
+        println!("[输入框内容]");
+    }
+}
+
+fn print_sources(sources: &[SourceStats]) {
+    if sources.is_empty() {
+        println!("历史记录为空。");
+        return;
+    }
+    println!("捕获次数  最近一次             来源程序");
+    for source in sources {
+        let time: DateTime<Local> = source.last_captured.into();
+        println!(
+            "{:>8}  {}  {}",
+            source.captures,
+            time.format("%Y-%m-%d %H:%M:%S"),
+            source.process.as_deref().unwrap_or("（未知）")
+        );
+    }
+}
+
+fn parse_mode(text: &str) -> Result<CaptureMode, String> {
+    text.parse()
+}
+
+fn parse_format(text: &str) -> Result<OutputFormat, String> {
+    text.parse()
+}
+
+// 只给端口时监听 127.0.0.1；其他程序能读到所有捕获，所以不允许监听外部地址
+fn parse_serve_addr(text: &str) -> Result<SocketAddr, String> {
+    let addr = match text.parse::<u16>() {
+        Ok(port) => SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        Err(_) => text
+            .parse::<SocketAddr>()
+            .map_err(|_| format!("无法识别的地址: {}", text))?,
+    };
+    if !addr.ip().is_loopback() {
+        return Err(format!("只允许监听本机地址: {}", addr.ip()));
+    }
+    Ok(addr)
+}
+
+// 解析绝对时间（本地时区）或相对时间
+fn parse_time(text: &str) -> Result<SystemTime, String> {
+    let text = text.trim();
+    let relative_unit = match text.chars().last() {
+        Some('m') => Some(60),
+        Some('h') => Some(60 * 60),
+        Some('d') => Some(24 * 60 * 60),
+        _ => None,
+    };
+    if let Some(unit) = relative_unit {
+        if let Ok(amount) = text[..text.len() - 1].parse::<u64>() {
+            // 太大的数会让乘法溢出，或者算出早于 1970 年的时间
+            return amount
+                .checked_mul(unit)
+                .and_then(|secs| SystemTime::now().checked_sub(Duration::from_secs(secs)))
+                .filter(|time| *time >= UNIX_EPOCH)
+                .ok_or_else(|| "时间超出范围".to_string());
+        }
+    }
+
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M"))
+        .or_else(|_| NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|d| d.and_hms_opt(0, 0, 0).unwrap()))
+        .map_err(|_| format!("无法识别的时间: {}", text))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("本地时区中不存在该时间: {}", text))
+}
+
+fn run_search(query: &SearchQuery) -> text_listener::history::Result<Vec<SearchHit>> {
+    HistoryStore::open_default()?.search(query)
+}
+
+fn print_hits(hits: &[SearchHit]) {
+    if hits.is_empty() {
+        println!("没有找到匹配的捕获。");
+        return;
+    }
+    println!("\n--- [找到 {} 条捕获] ---", hits.len());
+    for hit in hits {
+        print_entry_header(hit);
+        println!("{}", hit.snippet);
+    }
+    println!("--- [搜索结束] ---\n");
+}
+
+// 每条结果一行 JSON，附带高亮片段和相关度
+fn print_hits_json(hits: &[SearchHit]) {
+    for hit in hits {
+        let mut json = hit.entry.to_json();
+        json["snippet"] = hit.snippet.clone().into();
+        json["rank"] = hit.rank.into();
+        println!("{}", json);
+    }
+}
+
+// 菜单中的搜索：只支持关键词
+fn search_interactive() {
+    print!("请输入关键词（空格分隔）: ");
+    io::Write::flush(&mut io::stdout()).unwrap();
+    let mut line = String::new();
+    io::stdin().read_line(&mut line).unwrap();
+
+    let query = SearchQuery {
+        keywords: line.split_whitespace().map(str::to_string).collect(),
+        ..SearchQuery::default()
+    };
+    match run_search(&query) {
+        Ok(hits) => print_hits(&hits),
+        Err(e) => println!("[错误] 搜索历史失败: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_time() {
+        let before = SystemTime::now();
+        let parsed = parse_time(" 30m ").unwrap();
+        let after = SystemTime::now();
+        assert!(parsed >= before - Duration::from_secs(30 * 60));
+        assert!(parsed <= after - Duration::from_secs(30 * 60));
+        assert!(parse_time("2h").unwrap() < parse_time("1h").unwrap());
+        assert!(parse_time("0d").is_ok());
+    }
+
+    #[test]
+    fn relative_time_out_of_range() {
+        // 乘法溢出
+        assert_eq!(parse_time("99999999999999999d"), Err("时间超出范围".to_string()));
+        // 早于 1970 年
+        assert_eq!(parse_time("999999999d"), Err("时间超出范围".to_string()));
+    }
+
+    #[test]
+    fn absolute_time() {
+        let expected = |h, m, s| SystemTime::from(Local.with_ymd_and_hms(2024, 3, 5, h, m, s).earliest().unwrap());
+        assert_eq!(parse_time("2024-03-05"), Ok(expected(0, 0, 0)));
+        assert_eq!(parse_time("2024-03-05 14:30"), Ok(expected(14, 30, 0)));
+        assert_eq!(parse_time("2024-03-05 14:30:15"), Ok(expected(14, 30, 15)));
+        assert!(parse_time("昨天").is_err());
+        assert!(parse_time("m").is_err());
+        assert!(parse_time("-5m").is_err());
+    }
+}
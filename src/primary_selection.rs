@@ -0,0 +1,131 @@
+// --- 方法四：PRIMARY selection ---
+// Linux 上用鼠标选中文本时，被选中的内容会自动成为 PRIMARY selection（中键粘贴的就是它）。
+// 所以在 Linux 上不需要像方法三那样模拟 Ctrl+C：只要监听 PRIMARY 的变化再读取即可，
+// 完全不会碰用户的剪贴板。X11 下使用 XFixes，Wayland 下使用 data-control 协议。
+
+use crate::{
+    capture::{self, CaptureEvent, CaptureHandle, CaptureMode, CaptureSource, StopToken, WindowInfo},
+    clipboard_watch::{self, WaitOutcome},
+    rules::{self, RuleSet},
+};
+use arboard::{Clipboard, GetExtLinux, LinuxClipboardKind};
+use std::{sync::mpsc::Sender, time::Duration};
+
+/// PRIMARY selection 捕获源
+pub struct PrimarySelectionSource {
+    /// 最后一次变化后等待多久才读取。拖动选择时选区会连续变化，等它稳定下来再读。
+    pub settle: Duration,
+    /// 按 X11 的活动窗口匹配；Wayland 下拿不到窗口时只有不带条件的规则和默认动作会生效
+    pub rules: RuleSet,
+}
+
+impl Default for PrimarySelectionSource {
+    fn default() -> Self {
+        PrimarySelectionSource {
+            settle: Duration::from_millis(300),
+            rules: RuleSet::default(),
+        }
+    }
+}
+
+impl CaptureSource for PrimarySelectionSource {
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::PrimarySelection
+    }
+
+    fn run(&mut self, events: &Sender<CaptureEvent>, stop: &StopToken) {
+        let mut watcher = match clipboard_watch::open_primary_watcher() {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("[错误] 无法监听 PRIMARY selection: {}", e);
+                return;
+            }
+        };
+        // Wayland 后端自己读取内容，这时剪贴板初始化失败也没关系
+        let mut clipboard = Clipboard::new().ok();
+        eprintln!("[状态] PRIMARY selection 监听已启动（{}）。", watcher.name());
+
+        let mut last_text = String::new();
+        loop {
+            match watcher.wait_for_change(stop, Duration::from_secs(1)) {
+                Ok(WaitOutcome::Stopped) => break,
+                Ok(WaitOutcome::Timeout) => continue,
+                Ok(WaitOutcome::Changed) => {}
+                Err(e) => {
+                    eprintln!("[错误] PRIMARY selection 监听中断: {}", e);
+                    break;
+                }
+            }
+
+            // 等选区稳定：settle 时间内又有变化就继续等
+            loop {
+                match watcher.wait_for_change(stop, self.settle) {
+                    Ok(WaitOutcome::Changed) => continue,
+                    Ok(WaitOutcome::Timeout) => break,
+                    Ok(WaitOutcome::Stopped) => return,
+                    Err(e) => {
+                        eprintln!("[错误] PRIMARY selection 监听中断: {}", e);
+                        return;
+                    }
+                }
+            }
+
+            if stop.is_paused() {
+                continue;
+            }
+
+            // 选中通常发生在活动窗口里；被规则拒绝的窗口不读取内容
+            let window = WindowInfo::foreground();
+            if !self.rules.allows(window.as_ref()) {
+                continue;
+            }
+
+            // 密码管理器标记过的选区不读取内容
+            if watcher.marked_sensitive() {
+                last_text.clear();
+                if events.send(CaptureEvent::marked_sensitive(self.mode(), window)).is_err() {
+                    break;
+                }
+                continue;
+            }
+
+            let text = match watcher.take_content() {
+                Some(Some(content)) => content.text(),
+                Some(None) => continue,
+                None => match clipboard
+                    .as_mut()
+                    .map(|c| c.get().clipboard(LinuxClipboardKind::Primary).text())
+                {
+                    Some(Ok(text)) => text,
+                    // 选区被取消或 owner 已退出，这很常见，不需要报错
+                    _ => continue,
+                },
+            };
+            if text.trim().is_empty() || text == last_text {
+                continue;
+            }
+
+            last_text = text.clone();
+            if events.send(CaptureEvent::new(self.mode(), text, window)).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// 在后台线程启动 PRIMARY selection 监听
+pub fn start() -> CaptureHandle {
+    capture::start(PrimarySelectionSource::default())
+}
+
+pub fn run() {
+    println!("方法四：选中文本监听模式已启动。");
+    println!("请在任何窗口用鼠标选中一段文本，不需要复制，这里会显示出来。");
+    println!("提示：该模式直接读取 PRIMARY selection，不会模拟按键，也不会改动剪贴板。");
+    println!("退出方式：按回车键停止并返回菜单");
+
+    capture::run_until_enter(PrimarySelectionSource {
+        rules: rules::load_default_or_builtin(),
+        ..PrimarySelectionSource::default()
+    });
+}
@@ -0,0 +1,378 @@
+// --- 捕获规则 ---
+// 并不是每个窗口都适合捕获：密码管理器里的内容不该被记录，
+// 在终端里模拟 Ctrl+C 会发送 SIGINT 把正在运行的程序打断。
+// 规则按可执行文件名、窗口类名和窗口标题（正则）匹配，决定允许还是拒绝捕获。
+//
+// 规则文件是 TOML 格式，规则按顺序匹配，第一条命中的规则生效：
+//
+//     default = "allow"        # 没有规则命中时的动作，可省略
+//
+//     [[rule]]
+//     action = "deny"
+//     title = "(?i)网上银行"
+//
+//     [[rule]]
+//     action = "allow"
+//     process = "WindowsTerminal.exe"   # 覆盖下面的内置规则
+//
+// 用户规则之后还有一组内置规则（见 BUILTIN_RULES），拒绝 Windows 和 Linux 上的终端和常见的密码管理器。
+
+use crate::capture::WindowInfo;
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+// 内置规则，排在用户规则之后，用户可以用 allow 规则覆盖
+const BUILTIN_RULES: &str = r#"
+# Windows 控制台和 Windows Terminal：Ctrl+C 会中断正在运行的程序
+[[rule]]
+action = "deny"
+class = "ConsoleWindowClass"
+
+[[rule]]
+action = "deny"
+class = "CASCADIA_HOSTING_WINDOW_CLASS"
+
+[[rule]]
+action = "deny"
+class = "mintty"
+
+[[rule]]
+action = "deny"
+process = "KeePass.exe"
+
+[[rule]]
+action = "deny"
+process = "KeePassXC.exe"
+
+[[rule]]
+action = "deny"
+process = "1Password.exe"
+
+[[rule]]
+action = "deny"
+process = "Bitwarden.exe"
+
+# Linux 终端：进程名来自 /proc/<pid>/exe，类名是 WM_CLASS 的类名部分。
+# Terminator 是 Python 程序、xterm 可能不设置 _NET_WM_PID，所以按类名匹配
+[[rule]]
+action = "deny"
+process = "gnome-terminal-server"
+
+[[rule]]
+action = "deny"
+process = "konsole"
+
+[[rule]]
+action = "deny"
+process = "xterm"
+
+[[rule]]
+action = "deny"
+class = "XTerm"
+
+[[rule]]
+action = "deny"
+process = "xfce4-terminal"
+
+[[rule]]
+action = "deny"
+process = "tilix"
+
+[[rule]]
+action = "deny"
+class = "Terminator"
+
+[[rule]]
+action = "deny"
+process = "alacritty"
+
+[[rule]]
+action = "deny"
+process = "kitty"
+
+[[rule]]
+action = "deny"
+process = "wezterm-gui"
+
+[[rule]]
+action = "deny"
+process = "foot"
+
+# Linux 密码管理器。KeePass 2 运行在 Mono 里，进程名是 mono，只能按类名匹配
+[[rule]]
+action = "deny"
+process = "keepassxc"
+
+[[rule]]
+action = "deny"
+class = "KeePass2"
+
+[[rule]]
+action = "deny"
+process = "1password"
+
+[[rule]]
+action = "deny"
+process = "bitwarden"
+"#;
+
+/// 规则的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// 一条规则。所有给出的条件都满足才算命中；没有任何条件的规则命中所有窗口。
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub action: Action,
+    /// 可执行文件名，比如 `notepad.exe`，不区分大小写
+    pub process: Option<String>,
+    /// 窗口类名，不区分大小写
+    pub class: Option<String>,
+    /// 在窗口标题中搜索的正则表达式
+    pub title: Option<Regex>,
+}
+
+impl Rule {
+    pub fn matches(&self, window: Option<&WindowInfo>) -> bool {
+        let process_ok = match &self.process {
+            None => true,
+            Some(wanted) => window
+                .and_then(|w| w.process.as_deref())
+                .is_some_and(|process| process.eq_ignore_ascii_case(wanted)),
+        };
+        let class_ok = match &self.class {
+            None => true,
+            Some(wanted) => window
+                .and_then(|w| w.class.as_deref())
+                .is_some_and(|class| class.eq_ignore_ascii_case(wanted)),
+        };
+        let title_ok = match &self.title {
+            None => true,
+            Some(pattern) => window.is_some_and(|w| pattern.is_match(&w.title)),
+        };
+        process_ok && class_ok && title_ok
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            Action::Allow => "allow",
+            Action::Deny => "deny",
+        };
+        write!(f, "{}", action)?;
+        if let Some(process) = &self.process {
+            write!(f, " process={}", process)?;
+        }
+        if let Some(class) = &self.class {
+            write!(f, " class={}", class)?;
+        }
+        if let Some(title) = &self.title {
+            write!(f, " title=/{}/", title)?;
+        }
+        Ok(())
+    }
+}
+
+/// 规则文件解析失败
+#[derive(Debug)]
+pub struct RuleError(pub String);
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    default: Action,
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleEntry {
+    action: Action,
+    process: Option<String>,
+    class: Option<String>,
+    title: Option<String>,
+}
+
+/// 一组按顺序匹配的规则
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    default: Action,
+}
+
+impl Default for RuleSet {
+    /// 只有内置规则
+    fn default() -> Self {
+        RuleSet::parse("").expect("内置规则有误")
+    }
+}
+
+impl RuleSet {
+    /// 解析规则文件的内容，内置规则追加在后面
+    pub fn parse(text: &str) -> Result<RuleSet, RuleError> {
+        let file: RuleFile = toml::from_str(text).map_err(|e| RuleError(format!("规则文件格式错误: {}", e)))?;
+        let builtin: RuleFile = toml::from_str(BUILTIN_RULES).map_err(|e| RuleError(e.to_string()))?;
+
+        let mut rules = Vec::new();
+        for (index, entry) in file.rules.into_iter().chain(builtin.rules).enumerate() {
+            let title = match entry.title {
+                Some(pattern) => Some(
+                    Regex::new(&pattern)
+                        .map_err(|e| RuleError(format!("第 {} 条规则的标题正则有误: {}", index + 1, e)))?,
+                ),
+                None => None,
+            };
+            rules.push(Rule {
+                action: entry.action,
+                process: entry.process,
+                class: entry.class,
+                title,
+            });
+        }
+        Ok(RuleSet {
+            rules,
+            default: file.default,
+        })
+    }
+
+    /// 读取规则文件
+    pub fn load(path: &Path) -> Result<RuleSet, RuleError> {
+        let text = fs::read_to_string(path).map_err(|e| RuleError(format!("无法读取 {}: {}", path.display(), e)))?;
+        RuleSet::parse(&text).map_err(|e| RuleError(format!("{}: {}", path.display(), e)))
+    }
+
+    /// 读取默认位置的规则文件（见 `default_path`）；文件不存在时只使用内置规则
+    pub fn load_default() -> Result<RuleSet, RuleError> {
+        let path = default_path();
+        match fs::metadata(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(RuleSet::default()),
+            _ => RuleSet::load(&path),
+        }
+    }
+
+    /// 第一条命中的规则；没有命中时返回 None，表示使用默认动作
+    pub fn matching_rule(&self, window: Option<&WindowInfo>) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(window))
+    }
+
+    /// 对这个窗口应该采取的动作
+    pub fn decide(&self, window: Option<&WindowInfo>) -> Action {
+        self.matching_rule(window).map_or(self.default, |rule| rule.action)
+    }
+
+    pub fn allows(&self, window: Option<&WindowInfo>) -> bool {
+        self.decide(window) == Action::Allow
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn default_action(&self) -> Action {
+        self.default
+    }
+}
+
+/// 默认的规则文件位置。
+///
+/// 可以用环境变量 `TEXT_LISTENER_RULES` 指定；否则 Windows 上是 `%APPDATA%\text_listener\rules.toml`，
+/// 其他平台是 `$XDG_CONFIG_HOME/text_listener/rules.toml`（默认 `~/.config/text_listener/rules.toml`）。
+pub fn default_path() -> PathBuf {
+    match env::var_os("TEXT_LISTENER_RULES") {
+        Some(path) => PathBuf::from(path),
+        None => config_dir().join("rules.toml"),
+    }
+}
+
+// 配置文件所在的目录，规则和脱敏配置都放在这里
+pub(crate) fn config_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.unwrap_or_else(|| PathBuf::from(".")).join("text_listener")
+}
+
+/// 菜单模式使用：读取默认规则，出错时打印警告并只使用内置规则
+pub fn load_default_or_builtin() -> RuleSet {
+    RuleSet::load_default().unwrap_or_else(|e| {
+        eprintln!("[警告] {}，只使用内置规则。", e);
+        RuleSet::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(process: Option<&str>, class: Option<&str>) -> WindowInfo {
+        WindowInfo {
+            title: "窗口".to_string(),
+            process: process.map(str::to_string),
+            class: class.map(str::to_string),
+            pid: None,
+            exe_path: None,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn builtin_rules_deny_terminals_and_password_managers() {
+        let rules = RuleSet::default();
+        for process in ["KeePassXC.exe", "keepassxc", "bitwarden", "1password", "gnome-terminal-server", "konsole"] {
+            assert!(!rules.allows(Some(&window(Some(process), None))), "{}", process);
+        }
+        for class in ["ConsoleWindowClass", "XTerm", "Terminator", "KeePass2"] {
+            assert!(!rules.allows(Some(&window(None, Some(class)))), "{}", class);
+        }
+        assert!(rules.allows(Some(&window(Some("firefox"), Some("firefox")))));
+        assert!(rules.allows(None));
+    }
+
+    #[test]
+    fn user_rules_come_first() {
+        let rules = RuleSet::parse(
+            r#"
+            default = "deny"
+
+            [[rule]]
+            action = "allow"
+            process = "Konsole"
+
+            [[rule]]
+            action = "allow"
+            title = "(?i)notes"
+            "#,
+        )
+        .unwrap();
+        assert!(rules.allows(Some(&window(Some("konsole"), None))));
+        let mut notes = window(Some("gedit"), None);
+        notes.title = "My Notes".to_string();
+        assert!(rules.allows(Some(&notes)));
+        assert!(!rules.allows(Some(&window(Some("gedit"), None))));
+        assert!(!rules.allows(Some(&window(Some("xterm"), None))));
+    }
+}
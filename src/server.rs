@@ -0,0 +1,563 @@
+// --- 本地接口 ---
+// 翻译浮窗、笔记工具等其他本机程序想要实时拿到捕获的文本，而不是去解析控制台输出。
+// 这里提供一个只监听本机地址的小型 HTTP 服务，每个连接一个线程（有数量上限），和其余代码一样不依赖异步运行时：
+//
+//     GET  /api/events    SSE 推送捕获事件（每条一行 JSON）
+//     GET  /api/ws        WebSocket 推送捕获事件（每条一个文本消息）
+//     GET  /api/history   历史记录，参数 q（关键词，空格分隔）、mode、process、limit
+//     GET  /api/status    当前模式和是否暂停
+//     POST /api/pause     暂停捕获
+//     POST /api/resume    恢复捕获
+//
+// 浏览器里的任何网页都能向 localhost 发请求，所以带有 Origin 头的请求必须来自本机页面，
+// Host 头也必须是本机地址（防止 DNS 重绑定）。
+
+use crate::{
+    capture::{CaptureMode, StopToken},
+    clipboard_content::percent_decode,
+    history::{HistoryStore, SearchQuery},
+};
+use serde_json::{json, Value};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+// 没有新连接时多久检查一次停止信号
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+// 读取请求头的超时，防止半开的连接占住线程
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// 请求头的最大长度
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+// SSE 连接空闲这么久发一次注释行，顺便发现已经断开的客户端
+const SSE_KEEPALIVE: Duration = Duration::from_secs(15);
+// WebSocket 连接轮流检查新事件和客户端消息的间隔
+const WS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+// /api/history 一次最多返回的条数
+const MAX_HISTORY_LIMIT: usize = 1000;
+// 同时处理的连接数上限，超过时直接回复 503，避免无限制地创建线程
+const MAX_CONNECTIONS: usize = 64;
+// 拒绝连接之前读取请求的超时；不读完请求就关闭连接，客户端收到的是 RST 而不是 503
+const REJECT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// 把捕获事件广播给所有订阅者（SSE 和 WebSocket 连接）
+#[derive(Clone, Default)]
+pub struct EventFeed {
+    subscribers: Arc<Mutex<Vec<Sender<String>>>>,
+}
+
+impl EventFeed {
+    pub fn subscribe(&self) -> Receiver<String> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// 发送给所有订阅者，顺便清理已经断开的
+    pub fn publish(&self, event: &Value) {
+        let line = event.to_string();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(line.clone()).is_ok());
+    }
+
+    // 断开所有订阅者，它们的连接线程会随之结束
+    fn close(&self) {
+        self.subscribers.lock().unwrap().clear();
+    }
+}
+
+// 每个连接线程共享的状态
+#[derive(Clone)]
+struct Context {
+    mode: CaptureMode,
+    // 捕获源的停止信号，用来暂停和恢复
+    capture: StopToken,
+    feed: EventFeed,
+}
+
+/// 运行中的本地接口，丢弃时停止
+pub struct ApiServer {
+    addr: SocketAddr,
+    feed: EventFeed,
+    stop: StopToken,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ApiServer {
+    /// 在 `addr` 上启动服务。`capture` 是捕获源的停止信号，`/api/pause` 通过它暂停捕获
+    pub fn start(addr: SocketAddr, mode: CaptureMode, capture: StopToken) -> io::Result<ApiServer> {
+        if !addr.ip().is_loopback() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "只允许监听本机地址"));
+        }
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let feed = EventFeed::default();
+        let context = Context {
+            mode,
+            capture,
+            feed: feed.clone(),
+        };
+        let stop = StopToken::new();
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || accept_loop(listener, context, thread_stop));
+        Ok(ApiServer {
+            addr,
+            feed,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// 实际监听的地址（端口为 0 时由系统分配）
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 推送一个捕获事件
+    pub fn publish(&self, event: &Value) {
+        self.feed.publish(event);
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.stop.stop();
+        self.feed.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// 占用一个连接名额，连接线程结束时归还
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn acquire(active: &Arc<AtomicUsize>) -> Option<ConnectionSlot> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < MAX_CONNECTIONS).then_some(n + 1))
+            .ok()
+            .map(|_| ConnectionSlot(active.clone()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+fn accept_loop(listener: TcpListener, context: Context, stop: StopToken) {
+    let active = Arc::new(AtomicUsize::new(0));
+    while !stop.is_stopped() {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let Some(slot) = ConnectionSlot::acquire(&active) else {
+                    eprintln!("[警告] 本地接口的连接数已达上限（{}），拒绝新连接。", MAX_CONNECTIONS);
+                    let _ = reject_busy(stream);
+                    continue;
+                };
+                let context = context.clone();
+                thread::spawn(move || {
+                    let _slot = slot;
+                    if let Err(e) = handle_connection(stream, &context) {
+                        eprintln!("[警告] 本地接口连接出错: {}", e);
+                    }
+                });
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if stop.wait_timeout(ACCEPT_INTERVAL) {
+                    break;
+                }
+            }
+            Err(e) => {
+                eprintln!("[警告] 本地接口接受连接失败: {}", e);
+                if stop.wait_timeout(ACCEPT_INTERVAL) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// 在接受连接的线程里直接回复 503，读取请求的时间很短，不会拖住后面的连接太久
+fn reject_busy(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REJECT_TIMEOUT))?;
+    read_request(&stream)?;
+    respond_json(&mut stream, 503, &json!({ "error": "连接数过多" }))
+}
+
+// 解析出来的请求，只保留用得到的部分
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn read_request(stream: &TcpStream) -> io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream.take(MAX_HEADER_BYTES as u64));
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: parse_query(query),
+        headers: Vec::new(),
+    };
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            // 请求头太长或者连接中途关闭
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            request.headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(Some(request))
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&key.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+// 主机名部分是否为本机：localhost、127.0.0.1、[::1]，端口任意
+fn is_local_host(host: &str) -> bool {
+    let host = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+// 没有 Origin 头（非浏览器客户端）或者来自本机页面才允许访问
+fn is_allowed(request: &Request) -> bool {
+    let host_ok = request.header("Host").is_some_and(is_local_host);
+    let origin_ok = match request.header("Origin") {
+        None => true,
+        Some(origin) => origin
+            .strip_prefix("http://")
+            .or_else(|| origin.strip_prefix("https://"))
+            .is_some_and(is_local_host),
+    };
+    host_ok && origin_ok
+}
+
+fn handle_connection(mut stream: TcpStream, context: &Context) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let Some(request) = read_request(&stream)? else {
+        return Ok(());
+    };
+    if !is_allowed(&request) {
+        return respond_json(&mut stream, 403, &json!({ "error": "只接受来自本机的请求" }));
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/events") => stream_sse(stream, context),
+        ("GET", "/api/ws") => stream_websocket(stream, &request, context),
+        ("GET", "/api/history") => match query_history(&request) {
+            Ok(entries) => respond_json(&mut stream, 200, &entries),
+            Err(message) => respond_json(&mut stream, 400, &json!({ "error": message })),
+        },
+        ("GET", "/api/status") => respond_json(&mut stream, 200, &status(context)),
+        ("POST", "/api/pause") => {
+            context.capture.pause();
+            eprintln!("[状态] 已通过本地接口暂停捕获。");
+            respond_json(&mut stream, 200, &status(context))
+        }
+        ("POST", "/api/resume") => {
+            context.capture.resume();
+            eprintln!("[状态] 已通过本地接口恢复捕获。");
+            respond_json(&mut stream, 200, &status(context))
+        }
+        (_, "/api/events" | "/api/ws" | "/api/history" | "/api/status" | "/api/pause" | "/api/resume") => {
+            respond_json(&mut stream, 405, &json!({ "error": "不支持的请求方法" }))
+        }
+        _ => respond_json(&mut stream, 404, &json!({ "error": "没有这个接口" })),
+    }
+}
+
+fn status(context: &Context) -> Value {
+    json!({
+        "mode": context.mode.as_str(),
+        "paused": context.capture.is_paused(),
+    })
+}
+
+fn query_history(request: &Request) -> Result<Value, String> {
+    let mut query = SearchQuery::default();
+    if let Some(keywords) = request.param("q") {
+        query.keywords = keywords.split_whitespace().map(str::to_string).collect();
+    }
+    if let Some(mode) = request.param("mode") {
+        query.mode = Some(mode.parse()?);
+    }
+    if let Some(process) = request.param("process") {
+        query.process = Some(process.to_string());
+    }
+    if let Some(limit) = request.param("limit") {
+        let limit: usize = limit.parse().map_err(|_| format!("limit 必须是正整数: {}", limit))?;
+        query.limit = limit.min(MAX_HISTORY_LIMIT);
+    }
+    let hits = HistoryStore::open_default()
+        .and_then(|store| store.search(&query))
+        .map_err(|e| format!("读取历史失败: {}", e))?;
+    Ok(Value::Array(hits.iter().map(|hit| hit.entry.to_json()).collect()))
+}
+
+fn respond_json(stream: &mut TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn stream_sse(mut stream: TcpStream, context: &Context) -> io::Result<()> {
+    let events = context.feed.subscribe();
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream; charset=utf-8\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+    )?;
+    stream.flush()?;
+    loop {
+        let chunk = match events.recv_timeout(SSE_KEEPALIVE) {
+            Ok(line) => format!("data: {}\n\n", line),
+            Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        // 客户端断开时写入失败，结束这个连接
+        if stream.write_all(chunk.as_bytes()).and_then(|_| stream.flush()).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+fn stream_websocket(mut stream: TcpStream, request: &Request, context: &Context) -> io::Result<()> {
+    let upgrade = request
+        .header("Upgrade")
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    let Some(key) = request.header("Sec-WebSocket-Key").filter(|_| upgrade) else {
+        return respond_json(&mut stream, 400, &json!({ "error": "需要 WebSocket 握手" }));
+    };
+    let events = context.feed.subscribe();
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        derive_accept_key(key.as_bytes())
+    )?;
+    stream.flush()?;
+
+    // 读取设置短超时，这样同一个线程可以轮流处理推送和客户端发来的消息（ping、close）
+    stream.set_read_timeout(Some(WS_POLL_INTERVAL))?;
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    loop {
+        match events.recv_timeout(WS_POLL_INTERVAL) {
+            Ok(line) => {
+                if socket.send(Message::Text(line)).is_err() {
+                    return Ok(());
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                let _ = socket.close(None);
+                let _ = socket.flush();
+                return Ok(());
+            }
+        }
+        match socket.read() {
+            Ok(Message::Close(_)) => {
+                // 回复 close 帧
+                let _ = socket.flush();
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+            Err(_) => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: "/api/status".to_string(),
+            query: Vec::new(),
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn query_is_decoded() {
+        assert_eq!(
+            parse_query("q=%E4%BD%A0%E5%A5%BD+world&limit=5&&flag"),
+            [
+                ("q".to_string(), "你好 world".to_string()),
+                ("limit".to_string(), "5".to_string()),
+                ("flag".to_string(), String::new()),
+            ]
+        );
+        assert!(parse_query("").is_empty());
+    }
+
+    #[test]
+    fn local_hosts() {
+        for host in ["localhost", "LocalHost:8080", "127.0.0.1", "127.0.0.1:9000", "[::1]", "[::1]:80"] {
+            assert!(is_local_host(host), "{}", host);
+        }
+        for host in ["localhost.evil.com", "evil.com", "127.0.0.1.nip.io", "192.168.1.2:80", "[::2]", "null", ""] {
+            assert!(!is_local_host(host), "{}", host);
+        }
+    }
+
+    #[test]
+    fn allowed_requests() {
+        assert!(is_allowed(&request(&[("Host", "127.0.0.1:8080")])));
+        assert!(is_allowed(&request(&[("host", "localhost:8080"), ("origin", "http://localhost:3000")])));
+        assert!(is_allowed(&request(&[("Host", "[::1]:8080"), ("Origin", "https://127.0.0.1")])));
+
+        // 没有 Host 头，或者 DNS 重绑定过来的 Host
+        assert!(!is_allowed(&request(&[])));
+        assert!(!is_allowed(&request(&[("Host", "localhost.evil.com:8080")])));
+        // 其他网页，以及沙箱 iframe、file:// 页面发出的 "null"
+        assert!(!is_allowed(&request(&[("Host", "localhost"), ("Origin", "https://evil.com")])));
+        assert!(!is_allowed(&request(&[("Host", "localhost"), ("Origin", "http://localhost.evil.com")])));
+        assert!(!is_allowed(&request(&[("Host", "localhost"), ("Origin", "null")])));
+    }
+
+    // 发送一个请求，返回状态码和 JSON 响应体
+    fn send(addr: SocketAddr, method: &str, path: &str, headers: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\n{}\r\n", method, path, headers).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn serves_status_pause_and_resume() {
+        let capture = StopToken::new();
+        let server = ApiServer::start("127.0.0.1:0".parse().unwrap(), CaptureMode::ClipboardPoll, capture.clone()).unwrap();
+        let addr = server.local_addr();
+        let host = format!("Host: {}\r\n", addr);
+
+        let (status, body) = send(addr, "GET", "/api/status", &host);
+        assert_eq!(status, 200);
+        assert_eq!(body, json!({ "mode": CaptureMode::ClipboardPoll.as_str(), "paused": false }));
+
+        let (status, body) = send(addr, "POST", "/api/pause", &host);
+        assert_eq!((status, &body["paused"]), (200, &json!(true)));
+        assert!(capture.is_paused());
+        let (status, body) = send(addr, "POST", "/api/resume", &host);
+        assert_eq!((status, &body["paused"]), (200, &json!(false)));
+        assert!(!capture.is_paused());
+
+        assert_eq!(send(addr, "GET", "/api/nothing", &host).0, 404);
+        assert_eq!(send(addr, "GET", "/api/pause", &host).0, 405);
+    }
+
+    #[test]
+    fn rejects_foreign_origin_and_host() {
+        let capture = StopToken::new();
+        let server = ApiServer::start("127.0.0.1:0".parse().unwrap(), CaptureMode::ClipboardPoll, capture.clone()).unwrap();
+        let addr = server.local_addr();
+
+        let foreign_origin = format!("Host: {}\r\nOrigin: https://evil.com\r\n", addr);
+        assert_eq!(send(addr, "POST", "/api/pause", &foreign_origin).0, 403);
+        assert_eq!(send(addr, "POST", "/api/pause", "Host: evil.com\r\n").0, 403);
+        assert!(!capture.is_paused());
+    }
+
+    #[test]
+    fn limits_concurrent_connections() {
+        let server = ApiServer::start("127.0.0.1:0".parse().unwrap(), CaptureMode::ClipboardPoll, StopToken::new()).unwrap();
+        let addr = server.local_addr();
+
+        // SSE 连接会一直占着名额；读到响应头说明连接线程已经在运行
+        let streams: Vec<TcpStream> = (0..MAX_CONNECTIONS)
+            .map(|_| {
+                let mut stream = TcpStream::connect(addr).unwrap();
+                write!(stream, "GET /api/events HTTP/1.1\r\nHost: {}\r\n\r\n", addr).unwrap();
+                let mut head = [0; 12];
+                stream.read_exact(&mut head).unwrap();
+                assert_eq!(&head, b"HTTP/1.1 200");
+                stream
+            })
+            .collect();
+        assert_eq!(send(addr, "GET", "/api/status", &format!("Host: {}\r\n", addr)).0, 503);
+        drop(streams);
+    }
+}
@@ -0,0 +1,593 @@
+// --- 方法二：Windows UI Automation ---
+// 这是最“正确”但也是最复杂的方法。
+// 由于其极端复杂性，提供一个完整的、健壮的示例非常困难。
+// 下面的代码是一个“概念验证”，展示了其基本思路，但省略了大量的错误处理和复杂的逻辑。
+
+use crate::{
+    capture::{self, CaptureEvent, CaptureKind, CaptureMode, CaptureSource, ScreenRect, SelectedRange, StopToken, TextContext, WindowInfo},
+    rules::{self, RuleSet},
+};
+use std::{
+    collections::VecDeque,
+    ffi::c_void,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+use windows::{
+    core::{implement, ComInterface, Result as WinResult},
+    Win32::{
+        System::Com::{
+            CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+            COINIT_MULTITHREADED,
+        },
+        System::Ole::{SafeArrayAccessData, SafeArrayDestroy, SafeArrayGetLBound, SafeArrayGetUBound, SafeArrayUnaccessData},
+        UI::Accessibility::{
+            CUIAutomation, IUIAutomation, IUIAutomationTextPattern, IUIAutomationTreeWalker, IUIAutomationTextRange, UIA_TextPatternId,
+            IUIAutomationElement, UIA_ValuePatternId, IUIAutomationValuePattern,
+            UIA_EditControlTypeId, UIA_DocumentControlTypeId, UIA_TextControlTypeId,
+            TextPatternRangeEndpoint_End, TextPatternRangeEndpoint_Start, TextUnit_Character, TextUnit_Paragraph,
+            IUIAutomationEventHandler, IUIAutomationEventHandler_Impl,
+            IUIAutomationFocusChangedEventHandler, IUIAutomationFocusChangedEventHandler_Impl,
+            TreeScope_Subtree, UIA_EVENT_ID, UIA_Text_TextSelectionChangedEventId,
+        },
+        Foundation::{HWND, POINT},
+        UI::WindowsAndMessaging::{GetAncestor, GetForegroundWindow, GetCursorPos, WindowFromPoint, GA_ROOT},
+    },
+};
+
+// 尝试从元素获取选中的文本。多选区时返回每一段；没有选中任何文本时返回空列表
+unsafe fn try_get_selected_text(element: &IUIAutomationElement) -> Vec<SelectedRange> {
+    let mut ranges = Vec::new();
+    if is_password(element) {
+        return ranges;
+    }
+
+    if let Ok(pattern_unknown) = element.GetCurrentPattern(UIA_TextPatternId) {
+        if let Ok(text_pattern) = pattern_unknown.cast::<IUIAutomationTextPattern>() {
+            if let Ok(selection) = text_pattern.GetSelection() {
+                let selection_len = selection.Length().unwrap_or(0);
+                for i in 0..selection_len {
+                    if let Ok(range) = selection.GetElement(i) {
+                        if let Ok(text) = range.GetText(-1) {
+                            let text_str = text.to_string();
+                            if !text_str.trim().is_empty() {
+                                let paragraph = enclosing_paragraph(&range, &text_str);
+                                ranges.push(SelectedRange {
+                                    rects: bounding_rects(&range),
+                                    sentence: paragraph.as_ref().map(TextContext::sentence),
+                                    paragraph,
+                                    text: text_str,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    ranges
+}
+
+// 选区所在的段落，以及选区在段落中的位置。
+// UIA 没有“句子”这个文本单位，句子由调用方从段落里切出来
+unsafe fn enclosing_paragraph(range: &IUIAutomationTextRange, selected: &str) -> Option<TextContext> {
+    let paragraph = range.Clone().ok()?;
+    paragraph.ExpandToEnclosingUnit(TextUnit_Paragraph).ok()?;
+
+    // 选区跨越多个段落时，ExpandToEnclosingUnit 只保留起点所在的段落，
+    // 再把终点扩展到最后一个选中字符所在段落的末尾
+    if paragraph.CompareEndpoints(TextPatternRangeEndpoint_End, range, TextPatternRangeEndpoint_End).ok()? < 0 {
+        let tail = range.Clone().ok()?;
+        tail.MoveEndpointByRange(TextPatternRangeEndpoint_Start, range, TextPatternRangeEndpoint_End).ok()?;
+        tail.MoveEndpointByUnit(TextPatternRangeEndpoint_Start, TextUnit_Character, -1).ok()?;
+        tail.ExpandToEnclosingUnit(TextUnit_Paragraph).ok()?;
+        paragraph.MoveEndpointByRange(TextPatternRangeEndpoint_End, &tail, TextPatternRangeEndpoint_End).ok()?;
+    }
+    let text = paragraph.GetText(-1).ok()?.to_string();
+
+    // 段落开头到选区开头之间的文本长度就是选区的偏移
+    let prefix = paragraph.Clone().ok()?;
+    prefix.MoveEndpointByRange(TextPatternRangeEndpoint_End, range, TextPatternRangeEndpoint_Start).ok()?;
+    let length = text.chars().count();
+    let start = prefix.GetText(-1).ok()?.to_string().chars().count().min(length);
+    let end = (start + selected.chars().count()).min(length);
+    Some(TextContext { text, start, end })
+}
+
+// 读取输入框的全部内容（ValuePattern）。这不是用户选中的文本，只在开启 field_values 时使用
+unsafe fn try_get_field_value(element: &IUIAutomationElement) -> Option<String> {
+    if is_password(element) {
+        return None;
+    }
+    let pattern_unknown = element.GetCurrentPattern(UIA_ValuePatternId).ok()?;
+    let value_pattern = pattern_unknown.cast::<IUIAutomationValuePattern>().ok()?;
+    let value = value_pattern.CurrentValue().ok()?.to_string();
+    (!value.trim().is_empty()).then_some(value)
+}
+
+// 密码框的内容绝不读取；无法确定时也按密码框处理
+unsafe fn is_password(element: &IUIAutomationElement) -> bool {
+    element.CurrentIsPassword().map_or(true, |value| value.as_bool())
+}
+
+// 读取元素中的选中文本；没有选区且允许时退回到输入框的全部内容
+unsafe fn read_element(
+    element: &IUIAutomationElement,
+    field_values: bool,
+    window: Option<WindowInfo>,
+) -> Option<CaptureEvent> {
+    let ranges = try_get_selected_text(element);
+    if !ranges.is_empty() {
+        return Some(CaptureEvent::from_ranges(CaptureMode::UiAutomation, ranges, window));
+    }
+    if field_values {
+        let value = try_get_field_value(element)?;
+        return Some(CaptureEvent {
+            kind: CaptureKind::FieldValue,
+            ..CaptureEvent::new(CaptureMode::UiAutomation, value, window)
+        });
+    }
+    None
+}
+
+// 读取文本范围在屏幕上的矩形。
+// GetBoundingRectangles 返回一个 double 数组，每四个数是一个矩形的 left、top、width、height
+unsafe fn bounding_rects(range: &IUIAutomationTextRange) -> Vec<ScreenRect> {
+    let array = match range.GetBoundingRectangles() {
+        Ok(array) if !array.is_null() => array,
+        _ => return Vec::new(),
+    };
+
+    let mut rects = Vec::new();
+    if let (Ok(lower), Ok(upper)) = (SafeArrayGetLBound(array, 1), SafeArrayGetUBound(array, 1)) {
+        let count = (upper - lower + 1).max(0) as usize;
+        let mut data: *mut c_void = ptr::null_mut();
+        if count > 0 && SafeArrayAccessData(array, &mut data).is_ok() {
+            let values = std::slice::from_raw_parts(data as *const f64, count);
+            rects.extend(values.chunks_exact(4).map(|rect| ScreenRect {
+                left: rect[0],
+                top: rect[1],
+                width: rect[2],
+                height: rect[3],
+            }));
+            let _ = SafeArrayUnaccessData(array);
+        }
+    }
+    let _ = SafeArrayDestroy(array);
+    rects
+}
+
+// 检查元素是否是文本相关的控件
+unsafe fn is_text_element(element: &IUIAutomationElement) -> bool {
+    if let Ok(control_type) = element.CurrentControlType() {
+        let type_id = control_type.0;
+        type_id == UIA_EditControlTypeId.0 || 
+        type_id == UIA_DocumentControlTypeId.0 || 
+        type_id == UIA_TextControlTypeId.0
+    } else {
+        false
+    }
+}
+
+// 读取地址栏最多等待的时间，捕获不能因为浏览器没有响应而卡住
+const URL_TIMEOUT: Duration = Duration::from_millis(300);
+
+// 查找地址栏时最多检查的元素个数
+const URL_SEARCH_LIMIT: usize = 300;
+
+// 最多缓存多少个窗口的网址
+const URL_CACHE_SIZE: usize = 32;
+
+// 缓存网址用的键：窗口句柄和窗口标题
+type UrlKey = (isize, String);
+
+// 按窗口句柄和标题缓存的网址。同一个标签页不用每次都遍历浏览器界面；
+// 切换标签页或者跳转之后标题会变，就会重新读取
+static URL_CACHE: Mutex<VecDeque<(UrlKey, Option<String>)>> = Mutex::new(VecDeque::new());
+
+// 读取网址的工作线程，第一次用到时启动
+static URL_WORKER: OnceLock<Mutex<Sender<UrlRequest>>> = OnceLock::new();
+
+struct UrlRequest {
+    key: UrlKey,
+    reply: Sender<Option<String>>,
+}
+
+fn cached_url(key: &UrlKey) -> Option<Option<String>> {
+    let cache = URL_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.iter().find(|(cached, _)| cached == key).map(|(_, url)| url.clone())
+}
+
+fn cache_url(key: UrlKey, url: Option<String>) {
+    let mut cache = URL_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.retain(|(cached, _)| *cached != key);
+    if cache.len() >= URL_CACHE_SIZE {
+        cache.pop_front();
+    }
+    cache.push_back((key, url));
+}
+
+/// 浏览器窗口当前标签页的网址，读取失败或超时返回 None。
+///
+/// 调用方可能在任何线程上（包括方法三的钩子线程），所以查找放在一个单独的工作线程里，
+/// 它有自己的 COM 和 UIA 实例。超时的查找会在后台继续做完并写进缓存，不会再另开线程。
+pub(crate) fn browser_url(hwnd: HWND, title: &str) -> Option<String> {
+    let key = (hwnd.0, title.to_string());
+    if let Some(url) = cached_url(&key) {
+        return url;
+    }
+    let (reply, rx) = mpsc::channel();
+    let worker = URL_WORKER.get_or_init(|| Mutex::new(start_url_worker()));
+    let sent = match worker.lock() {
+        Ok(worker) => worker.send(UrlRequest { key, reply }).is_ok(),
+        Err(_) => false,
+    };
+    if !sent {
+        return None;
+    }
+    rx.recv_timeout(URL_TIMEOUT).ok().flatten()
+}
+
+fn start_url_worker() -> Sender<UrlRequest> {
+    let (tx, rx) = mpsc::channel::<UrlRequest>();
+    thread::spawn(move || unsafe {
+        if CoInitializeEx(None, COINIT_MULTITHREADED).is_err() {
+            return;
+        }
+        let automation = CoCreateInstance::<_, IUIAutomation>(&CUIAutomation, None, CLSCTX_INPROC_SERVER).ok();
+        for request in rx {
+            // 排队的时候前面的请求可能已经读到了同一个网址
+            let url = match cached_url(&request.key) {
+                Some(url) => url,
+                None => {
+                    let url = automation.as_ref().and_then(|automation| {
+                        let window = automation.ElementFromHandle(HWND(request.key.0)).ok()?;
+                        let walker = automation.ControlViewWalker().ok()?;
+                        find_address_bar(&walker, window)
+                    });
+                    cache_url(request.key, url.clone());
+                    url
+                }
+            };
+            let _ = request.reply.send(url);
+        }
+        drop(automation);
+        CoUninitialize();
+    });
+    tx
+}
+
+// 地址栏的名称随界面语言变化，所以按控件类型找：浏览器界面里第一个有内容的编辑框就是地址栏。
+// 按层次遍历，并且不展开网页内容（Document），否则会走进整个页面
+unsafe fn find_address_bar(walker: &IUIAutomationTreeWalker, window: IUIAutomationElement) -> Option<String> {
+    let mut queue = VecDeque::from([window]);
+    let mut checked = 0;
+    while let Some(element) = queue.pop_front() {
+        checked += 1;
+        if checked > URL_SEARCH_LIMIT {
+            break;
+        }
+        match element.CurrentControlType() {
+            Ok(control_type) if control_type == UIA_EditControlTypeId => {
+                if let Some(url) = try_get_field_value(&element) {
+                    return Some(url);
+                }
+                continue;
+            }
+            Ok(control_type) if control_type == UIA_DocumentControlTypeId => continue,
+            _ => {}
+        }
+        let mut child = walker.GetFirstChildElement(&element).ok();
+        while let Some(current) = child {
+            child = walker.GetNextSiblingElement(&current).ok();
+            queue.push_back(current);
+        }
+    }
+    None
+}
+
+/// UI Automation 捕获源：订阅焦点变化和文本选择变化事件，读取选中的文本。
+///
+/// 注册事件失败时（比如旧系统上的 UIA 不支持），退回到每 500ms 轮询焦点元素和鼠标下方元素。
+#[derive(Debug, Clone, Default)]
+pub struct UiAutomationSource {
+    /// 被拒绝的窗口不会读取其中的文本
+    pub rules: RuleSet,
+    /// 没有选中文本时输出输入框的全部内容（`CaptureKind::FieldValue`）。密码框始终不读取
+    pub field_values: bool,
+}
+
+impl CaptureSource for UiAutomationSource {
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::UiAutomation
+    }
+
+    fn run(&mut self, events: &Sender<CaptureEvent>, stop: &StopToken) {
+        unsafe {
+            if let Err(e) = CoInitializeEx(None, COINIT_MULTITHREADED) {
+                eprintln!("[错误] COM 初始化失败: {:?}", e);
+                return;
+            }
+
+            let automation: IUIAutomation = match CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER) {
+                Ok(inst) => inst,
+                Err(e) => {
+                    eprintln!("[错误] 创建 UI Automation 实例失败: {:?}", e);
+                    CoUninitialize();
+                    return;
+                }
+            };
+
+            if let Err(e) = run_events(&automation, events, stop, self) {
+                eprintln!("[警告] 注册 UI Automation 事件失败: {:?}，改为轮询。", e);
+                run_polling(&automation, events, stop, self);
+            }
+
+            eprintln!("[状态] UI Automation 监听已停止。");
+            drop(automation);
+            CoUninitialize();
+        }
+    }
+}
+
+pub fn run() {
+    println!("方法二：改进的 UI Automation 模式已启动。");
+    println!("这个版本会订阅焦点变化和文本选择事件，选中文本后立即输出。");
+    println!("支持多种控件类型：编辑框、文档、富文本等。");
+    println!("退出方式：按回车键停止并返回菜单");
+    println!("\n[提示] 请在不同的应用中选择文本，程序会自动检测...");
+
+    capture::run_until_enter(UiAutomationSource {
+        rules: rules::load_default_or_builtin(),
+        field_values: false,
+    });
+}
+
+// 前台窗口换了，或者同一个窗口的标题变了（切换了标签页或文档）
+fn window_changed(last_window: Option<HWND>, last_info: Option<&WindowInfo>, window: HWND, info: &WindowInfo) -> bool {
+    last_window != Some(window) || last_info.is_none_or(|last| last.title != info.title)
+}
+
+// 事件处理器之间共享的状态。UIA 在自己的工作线程上调用处理器，所以都放在锁里
+struct SelectionState {
+    last_window: Option<HWND>,
+    window_info: Option<WindowInfo>,
+    last_text: String,
+}
+
+// 同时处理焦点变化和文本选择变化事件
+#[implement(IUIAutomationFocusChangedEventHandler, IUIAutomationEventHandler)]
+struct SelectionHandler {
+    events: Sender<CaptureEvent>,
+    rules: RuleSet,
+    field_values: bool,
+    stop: StopToken,
+    state: Mutex<SelectionState>,
+    // 接收端已经关闭，主线程看到后停止监听
+    closed: Arc<AtomicBool>,
+}
+
+impl SelectionHandler {
+    // 每次检查都重新读取前台窗口：同一个窗口里切换标签页或文档时只有标题会变，
+    // 规则必须按当前的标题来判断
+    unsafe fn update_window(&self, state: &mut SelectionState) {
+        let current_window = GetForegroundWindow();
+        if current_window.0 == 0 {
+            return;
+        }
+        let info = WindowInfo::from_hwnd(current_window);
+        if window_changed(state.last_window, state.window_info.as_ref(), current_window, &info) {
+            eprintln!("[事件] 窗口切换到: {}", info.title);
+            if !self.rules.allows(Some(&info)) {
+                eprintln!("[规则] 不读取该窗口中的文本。");
+            }
+        }
+        state.window_info = Some(info);
+        state.last_window = Some(current_window);
+    }
+
+    unsafe fn check_selection(&self, element: &IUIAutomationElement) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        self.update_window(&mut state);
+
+        if self.stop.is_paused() || !self.rules.allows(state.window_info.as_ref()) || !is_text_element(element) {
+            return;
+        }
+        let Some(mut event) = read_element(element, self.field_values, state.window_info.clone()) else {
+            return;
+        };
+        // 避免重复显示相同内容
+        if event.text == state.last_text || event.text.len() <= 2 {
+            return;
+        }
+        state.last_text = event.text.clone();
+        let hwnd = state.last_window;
+        // 读取网址可能要等几百毫秒，先释放状态锁，别让同时到来的选区和焦点事件排队
+        drop(state);
+
+        if let Some(hwnd) = hwnd {
+            event.window = event.window.map(|window| window.with_browser_url(hwnd));
+        }
+        if self.events.send(event).is_err() {
+            self.closed.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl IUIAutomationFocusChangedEventHandler_Impl for SelectionHandler {
+    fn HandleFocusChangedEvent(&self, sender: Option<&IUIAutomationElement>) -> WinResult<()> {
+        // 焦点移到一个已经有选区的控件上时也输出一次
+        if let Some(element) = sender {
+            unsafe { self.check_selection(element) };
+        }
+        Ok(())
+    }
+}
+
+impl IUIAutomationEventHandler_Impl for SelectionHandler {
+    fn HandleAutomationEvent(&self, sender: Option<&IUIAutomationElement>, eventid: UIA_EVENT_ID) -> WinResult<()> {
+        if eventid == UIA_Text_TextSelectionChangedEventId {
+            if let Some(element) = sender {
+                unsafe { self.check_selection(element) };
+            }
+        }
+        Ok(())
+    }
+}
+
+// 注册事件处理器，然后等待停止。只有注册失败时才返回 Err
+unsafe fn run_events(
+    automation: &IUIAutomation,
+    events: &Sender<CaptureEvent>,
+    stop: &StopToken,
+    source: &UiAutomationSource,
+) -> WinResult<()> {
+    let closed = Arc::new(AtomicBool::new(false));
+    let handler = SelectionHandler {
+        events: events.clone(),
+        rules: source.rules.clone(),
+        field_values: source.field_values,
+        stop: stop.clone(),
+        state: Mutex::new(SelectionState {
+            last_window: None,
+            window_info: None,
+            last_text: String::new(),
+        }),
+        closed: closed.clone(),
+    };
+    let focus_handler: IUIAutomationFocusChangedEventHandler = handler.into();
+    let selection_handler: IUIAutomationEventHandler = focus_handler.cast()?;
+
+    let root = automation.GetRootElement()?;
+    automation.AddFocusChangedEventHandler(None, &focus_handler)?;
+    if let Err(e) = automation.AddAutomationEventHandler(
+        UIA_Text_TextSelectionChangedEventId,
+        &root,
+        TreeScope_Subtree,
+        None,
+        &selection_handler,
+    ) {
+        let _ = automation.RemoveAllEventHandlers();
+        return Err(e);
+    }
+
+    eprintln!("[状态] UI Automation 已初始化，开始监听事件...");
+
+    while !stop.wait_timeout(Duration::from_millis(200)) {
+        if closed.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+
+    // 注销之后 UIA 不会再调用处理器，可以安全地释放它们
+    let _ = automation.RemoveAllEventHandlers();
+    Ok(())
+}
+
+// 事件不可用时的退路：轮询焦点元素和鼠标下方元素
+fn run_polling(automation: &IUIAutomation, events: &Sender<CaptureEvent>, stop: &StopToken, source: &UiAutomationSource) {
+    let rules = &source.rules;
+    unsafe {
+        eprintln!("[状态] UI Automation 已初始化，开始监听...");
+
+        let mut last_window: Option<HWND> = None;
+        let mut window_info: Option<WindowInfo> = None;
+        let mut last_text = String::new();
+        let mut check_count = 0;
+
+        loop {
+            if stop.is_stopped() {
+                break;
+            }
+
+            check_count += 1;
+            if check_count % 20 == 0 { // 每10秒显示一次状态
+                eprintln!("[状态] 持续监听中... (已检查 {} 次)", check_count);
+            }
+
+            if stop.is_paused() {
+                stop.wait_timeout(Duration::from_millis(500));
+                continue;
+            }
+
+            // 获取当前前台窗口
+            let current_window = GetForegroundWindow();
+            if current_window.0 == 0 {
+                stop.wait_timeout(Duration::from_millis(500));
+                continue;
+            }
+
+            // 每次都重新读取窗口信息，切换标签页后规则按新的标题判断
+            let info = WindowInfo::from_hwnd(current_window);
+            if window_changed(last_window, window_info.as_ref(), current_window, &info) {
+                eprintln!("[事件] 窗口切换到: {}", info.title);
+                if !rules.allows(Some(&info)) {
+                    eprintln!("[规则] 不读取该窗口中的文本。");
+                }
+            }
+            window_info = Some(info);
+            last_window = Some(current_window);
+
+            // 尝试获取焦点元素
+            match automation.GetFocusedElement() {
+                Ok(focused_element) => {
+                    // 检查规则和是否是文本相关元素
+                    if rules.allows(window_info.as_ref()) && is_text_element(&focused_element) {
+                        if let Some(mut event) = read_element(&focused_element, source.field_values, window_info.clone()) {
+                            // 避免重复显示相同内容
+                            if event.text != last_text && event.text.len() > 2 {
+                                last_text = event.text.clone();
+                                event.window = event.window.map(|window| window.with_browser_url(current_window));
+                                if events.send(event).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+
+                    // 也尝试获取鼠标位置的元素
+                    let mut cursor_pos = POINT { x: 0, y: 0 };
+                    if GetCursorPos(&mut cursor_pos).is_ok() {
+                        let hwnd_under_cursor = WindowFromPoint(cursor_pos);
+                        if hwnd_under_cursor.0 != 0 && hwnd_under_cursor != current_window {
+                            // 鼠标下方的窗口不一定是前台窗口，单独读取它所在顶层窗口的信息来匹配规则
+                            let cursor_root = GetAncestor(hwnd_under_cursor, GA_ROOT);
+                            let cursor_window = WindowInfo::from_hwnd(cursor_root);
+                            if rules.allows(Some(&cursor_window)) {
+                                if let Ok(element_under_cursor) = automation.ElementFromHandle(hwnd_under_cursor) {
+                                    if is_text_element(&element_under_cursor) {
+                                        if let Some(mut event) = read_element(&element_under_cursor, source.field_values, Some(cursor_window)) {
+                                            if event.text != last_text && event.text.len() > 2 {
+                                                last_text = event.text.clone();
+                                                event.window = event.window.map(|window| window.with_browser_url(cursor_root));
+                                                if events.send(event).is_err() {
+                                                    break;
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Err(_) => {
+                    // 焦点元素获取失败，这很常见，不需要报错
+                }
+            }
+
+            if stop.wait_timeout(Duration::from_millis(500)) { // 每500ms检查一次
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+// AT-SPI 捕获源的集成测试：在无障碍总线上注册一个假的程序，发出选择变化事件，检查捕获到的内容。
+//
+// 需要一条带注册中心（at-spi2-registryd）的无障碍总线，没有桌面的环境里可以这样运行：
+//
+//     dbus-run-session -- sh -c '
+//         /usr/libexec/at-spi-bus-launcher --launch-immediately & sleep 1
+//         cargo test --test atspi -- --ignored'
+#![cfg(target_os = "linux")]
+
+use std::{collections::HashMap, env, time::Duration};
+use text_listener::{
+    atspi::AtspiSource,
+    capture::{self, CaptureKind, ScreenRect},
+};
+use zbus::{
+    blocking::{connection, Connection},
+    interface,
+    zvariant::{OwnedObjectPath, Value},
+};
+
+const TEXT_PATH: &str = "/org/a11y/atspi/accessible/text";
+const FRAME_PATH: &str = "/org/a11y/atspi/accessible/frame";
+
+// 三个段落，每段以换行结束；句子以句号或换行结束
+const CONTENT: &str = "第一句。第二句很短。\n第二段只有一句。\n第三段。";
+
+// AtspiRole
+const ROLE_FRAME: u32 = 23;
+const ROLE_TEXT: u32 = 61;
+
+// AtspiTextGranularity
+const GRANULARITY_SENTENCE: u32 = 2;
+
+struct Accessible {
+    role: u32,
+    name: String,
+    parent: (String, OwnedObjectPath),
+}
+
+#[interface(name = "org.a11y.atspi.Accessible")]
+impl Accessible {
+    fn get_role(&self) -> u32 {
+        self.role
+    }
+
+    #[zbus(property)]
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[zbus(property)]
+    fn parent(&self) -> (String, OwnedObjectPath) {
+        self.parent.clone()
+    }
+}
+
+// 选中了“短。\n第二段”，跨越两个句子和两个段落。起点和终点故意反过来给出
+struct Text {
+    selection: (i32, i32),
+}
+
+impl Text {
+    fn chars() -> Vec<char> {
+        CONTENT.chars().collect()
+    }
+
+    // offset 所在的单位：在终止符之后结束
+    fn unit_at(offset: i32, granularity: u32) -> (i32, i32) {
+        let chars = Text::chars();
+        let ends = |c: char| c == '\n' || (granularity == GRANULARITY_SENTENCE && c == '。');
+        let offset = (offset.max(0) as usize).min(chars.len().saturating_sub(1));
+        let start = (0..offset).rev().find(|&i| ends(chars[i])).map_or(0, |i| i + 1);
+        let end = (offset..chars.len()).find(|&i| ends(chars[i])).map_or(chars.len(), |i| i + 1);
+        (start as i32, end as i32)
+    }
+
+    // 和真实的程序一样，范围不合法时返回空字符串
+    fn slice(start: i32, end: i32) -> String {
+        let chars = Text::chars();
+        let end = if end < 0 { chars.len() } else { (end as usize).min(chars.len()) };
+        chars.get(start.max(0) as usize..end).map(|part| part.iter().collect()).unwrap_or_default()
+    }
+}
+
+#[interface(name = "org.a11y.atspi.Text")]
+impl Text {
+    fn get_n_selections(&self) -> i32 {
+        1
+    }
+
+    fn get_selection(&self, _index: i32) -> (i32, i32) {
+        (self.selection.1, self.selection.0)
+    }
+
+    fn get_text(&self, start: i32, end: i32) -> String {
+        Text::slice(start, end)
+    }
+
+    fn get_string_at_offset(&self, offset: i32, granularity: u32) -> (String, i32, i32) {
+        let (start, end) = Text::unit_at(offset, granularity);
+        (Text::slice(start, end), start, end)
+    }
+
+    fn get_range_extents(&self, _start: i32, _end: i32, _coord_type: u32) -> (i32, i32, i32, i32) {
+        (10, 20, 100, 16)
+    }
+}
+
+fn a11y_bus_address() -> String {
+    match env::var("AT_SPI_BUS_ADDRESS") {
+        Ok(address) if !address.is_empty() => address,
+        _ => {
+            let session = Connection::session().expect("需要会话总线（用 dbus-run-session 运行）");
+            session
+                .call_method(Some("org.a11y.Bus"), "/org/a11y/bus", Some("org.a11y.Bus"), "GetAddress", &())
+                .expect("需要无障碍总线（先启动 at-spi-bus-launcher）")
+                .body()
+                .deserialize()
+                .unwrap()
+        }
+    }
+}
+
+#[test]
+#[ignore = "需要无障碍总线，运行方法见文件开头"]
+fn captures_selection_with_context() {
+    let address = a11y_bus_address();
+    let handle = capture::start(AtspiSource::default());
+
+    // 先连上总线拿到自己的名字，再注册对象：Parent 属性里要用到它
+    let app = connection::Builder::address(address.as_str()).unwrap().build().unwrap();
+    let name = app.unique_name().unwrap().to_string();
+    let null = OwnedObjectPath::try_from("/org/a11y/atspi/null").unwrap();
+    let server = app.object_server();
+    server
+        .at(
+            FRAME_PATH,
+            Accessible {
+                role: ROLE_FRAME,
+                name: "测试窗口".to_string(),
+                parent: (name.clone(), null),
+            },
+        )
+        .unwrap();
+    server
+        .at(
+            TEXT_PATH,
+            Accessible {
+                role: ROLE_TEXT,
+                name: String::new(),
+                parent: (name, OwnedObjectPath::try_from(FRAME_PATH).unwrap()),
+            },
+        )
+        .unwrap();
+    server.at(TEXT_PATH, Text { selection: (8, 14) }).unwrap();
+
+    // 捕获源登记事件需要一点时间，在收到捕获之前反复发出事件
+    let mut received = None;
+    for _ in 0..25 {
+        let properties: HashMap<&str, Value> = HashMap::new();
+        app.emit_signal(
+            None::<&str>,
+            TEXT_PATH,
+            "org.a11y.atspi.Event.Object",
+            "TextSelectionChanged",
+            &("", 0i32, 0i32, Value::from(0i32), properties),
+        )
+        .unwrap();
+        if let Ok(event) = handle.events().recv_timeout(Duration::from_millis(200)) {
+            received = Some(event);
+            break;
+        }
+    }
+    // 只发出停止信号：出错时捕获源可能还卡在某个调用里，不等它退出
+    handle.stop();
+    let event = received.expect("没有收到捕获");
+
+    assert_eq!(event.text, "短。\n第二段");
+    assert_eq!(event.kind, CaptureKind::Selection);
+    let window = event.window.expect("没有来源窗口");
+    assert_eq!(window.title, "测试窗口");
+    assert_eq!(window.pid, Some(std::process::id()));
+
+    let range = &event.ranges[0];
+    assert_eq!(
+        range.rects,
+        [ScreenRect {
+            left: 10.0,
+            top: 20.0,
+            width: 100.0,
+            height: 16.0
+        }]
+    );
+    // 句子和段落都扩展到了最后一个选中字符所在单位的末尾
+    let sentence = range.sentence.as_ref().expect("没有所在句子");
+    assert_eq!(sentence.text, "第二句很短。\n第二段只有一句。");
+    assert_eq!((sentence.start, sentence.end), (4, 10));
+    let paragraph = range.paragraph.as_ref().expect("没有所在段落");
+    assert_eq!(paragraph.text, "第一句。第二句很短。\n第二段只有一句。\n");
+    assert_eq!((paragraph.start, paragraph.end), (8, 14));
+}
@@ -0,0 +1,92 @@
+// Wayland selection 监听的集成测试，需要支持 data-control 协议的合成器和 wl-clipboard 里的 wl-copy。
+// 没有桌面的环境里可以用无头的 Sway：
+//
+//     export XDG_RUNTIME_DIR=$(mktemp -d)
+//     WLR_BACKENDS=headless WLR_LIBINPUT_NO_DEVICES=1 sway & sleep 1
+//     WAYLAND_DISPLAY=wayland-1 cargo test --test wayland_selection -- --ignored
+#![cfg(target_os = "linux")]
+
+use std::{
+    env,
+    process::Command,
+    time::{Duration, Instant},
+};
+use text_listener::{
+    capture::StopToken,
+    clipboard_content::{ClipboardContent, MIME_HTML},
+    clipboard_watch::{ClipboardWatcher, WaitOutcome, WaylandSelectionWatcher},
+};
+
+fn require_wayland() {
+    assert!(
+        env::var_os("WAYLAND_DISPLAY").is_some(),
+        "需要 Wayland 合成器：设置 WAYLAND_DISPLAY 后再运行"
+    );
+}
+
+// 用 wl-copy 写入 selection。wl-copy 会留在后台提供内容，直到被下一次复制替换
+fn wl_copy(args: &[&str]) {
+    let status = Command::new("wl-copy").args(args).status().expect("需要 wl-clipboard 里的 wl-copy");
+    assert!(status.success(), "wl-copy {:?} 失败", args);
+}
+
+// 等到内容满足条件的那次变化；合成器可能先报告别的变化（比如上一个测试留下的内容）
+fn wait_for_content(
+    watcher: &mut WaylandSelectionWatcher,
+    matches: impl Fn(&ClipboardContent) -> bool,
+) -> ClipboardContent {
+    let stop = StopToken::new();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if watcher.wait_for_change(&stop, Duration::from_millis(500)).unwrap() != WaitOutcome::Changed {
+            continue;
+        }
+        if let Some(Some(content)) = watcher.take_content() {
+            if matches(&content) {
+                return content;
+            }
+        }
+    }
+    panic!("5 秒内没有等到期望的内容");
+}
+
+#[test]
+#[ignore = "需要 Wayland 合成器和 wl-copy，运行方法见文件开头"]
+fn clipboard_watcher_reads_text() {
+    require_wayland();
+    let mut watcher = WaylandSelectionWatcher::clipboard().unwrap();
+    wl_copy(&["复制的文本"]);
+
+    let content = wait_for_content(&mut watcher, |content| content.text() == "复制的文本");
+    assert_eq!(content, ClipboardContent::Text("复制的文本".to_string()));
+    assert!(!watcher.marked_sensitive());
+}
+
+#[test]
+#[ignore = "需要 Wayland 合成器和 wl-copy，运行方法见文件开头"]
+fn clipboard_watcher_reads_html() {
+    require_wayland();
+    let mut watcher = WaylandSelectionWatcher::clipboard().unwrap();
+    wl_copy(&["--type", MIME_HTML, "<b>加粗</b>的文本"]);
+
+    let content = wait_for_content(&mut watcher, |content| matches!(content, ClipboardContent::Html { .. }));
+    assert_eq!(content.data().unwrap(), "<b>加粗</b>的文本".as_bytes());
+    assert!(content.text().contains("加粗"));
+    assert!(watcher.mime_types().iter().any(|mime| mime == MIME_HTML));
+}
+
+#[test]
+#[ignore = "需要 Wayland 合成器和 wl-copy，运行方法见文件开头"]
+fn primary_watcher_reads_selection() {
+    require_wayland();
+    let mut watcher = WaylandSelectionWatcher::primary().unwrap();
+    wl_copy(&["--primary", "选中的文本"]);
+
+    let content = wait_for_content(&mut watcher, |content| content.text() == "选中的文本");
+    assert_eq!(content, ClipboardContent::Text("选中的文本".to_string()));
+
+    // 停止信号立即生效
+    let stop = StopToken::new();
+    stop.stop();
+    assert_eq!(watcher.wait_for_change(&stop, Duration::from_secs(5)).unwrap(), WaitOutcome::Stopped);
+}
@@ -0,0 +1,89 @@
+// X11 selection 监听（方法四和剪贴板通知）的集成测试，需要 X 服务器。没有桌面的环境里可以用 Xvfb：
+//
+//     Xvfb :99 & sleep 1
+//     DISPLAY=:99 cargo test --test x11_selection -- --ignored
+//
+// 测试用 arboard 扮演被选中或复制文本的程序，所以不需要别的工具。
+#![cfg(target_os = "linux")]
+
+use arboard::{Clipboard, LinuxClipboardKind, SetExtLinux};
+use std::{env, thread, time::Duration};
+use text_listener::{
+    capture::{self, CaptureMode, StopToken},
+    clipboard_watch::{self, ClipboardWatcher, WaitOutcome, X11SelectionWatcher},
+    primary_selection::PrimarySelectionSource,
+};
+
+fn require_display() {
+    assert!(env::var_os("DISPLAY").is_some(), "需要 X 服务器：设置 DISPLAY 后再运行");
+}
+
+#[test]
+#[ignore = "需要 X 服务器（DISPLAY），运行方法见文件开头"]
+fn watcher_reports_primary_changes() {
+    require_display();
+    let mut watcher = X11SelectionWatcher::new(b"PRIMARY").unwrap();
+    let stop = StopToken::new();
+
+    let mut clipboard = Clipboard::new().unwrap();
+    clipboard.set().clipboard(LinuxClipboardKind::Primary).text("选中的文本").unwrap();
+    assert_eq!(watcher.wait_for_change(&stop, Duration::from_secs(5)).unwrap(), WaitOutcome::Changed);
+    assert!(watcher.last_timestamp().is_some());
+    assert!(!watcher.marked_sensitive());
+
+    // 没有新的变化时超时，停止信号立即生效
+    assert_eq!(watcher.wait_for_change(&stop, Duration::from_millis(200)).unwrap(), WaitOutcome::Timeout);
+    stop.stop();
+    assert_eq!(watcher.wait_for_change(&stop, Duration::from_secs(5)).unwrap(), WaitOutcome::Stopped);
+}
+
+#[test]
+#[ignore = "需要 X 服务器（DISPLAY），运行方法见文件开头"]
+fn watcher_sees_password_manager_hint() {
+    require_display();
+    let mut watcher = X11SelectionWatcher::clipboard().unwrap();
+    let stop = StopToken::new();
+
+    // exclude_from_history 会附带 x-kde-passwordManagerHint，和 KeePassXC 一样
+    let mut clipboard = Clipboard::new().unwrap();
+    clipboard.set().exclude_from_history().text("hunter2").unwrap();
+    assert_eq!(watcher.wait_for_change(&stop, Duration::from_secs(5)).unwrap(), WaitOutcome::Changed);
+    assert!(watcher.marked_sensitive());
+    // 轮询时用的检查复用同一个连接，连续调用结果一致
+    assert!(clipboard_watch::clipboard_marked_sensitive());
+    assert!(clipboard_watch::clipboard_marked_sensitive());
+
+    clipboard.set_text("普通的复制").unwrap();
+    assert_eq!(watcher.wait_for_change(&stop, Duration::from_secs(5)).unwrap(), WaitOutcome::Changed);
+    assert!(!watcher.marked_sensitive());
+    assert!(!clipboard_watch::clipboard_marked_sensitive());
+}
+
+#[test]
+#[ignore = "需要 X 服务器（DISPLAY），运行方法见文件开头"]
+fn primary_source_captures_selection() {
+    require_display();
+    let handle = capture::start(PrimarySelectionSource {
+        settle: Duration::from_millis(50),
+        ..PrimarySelectionSource::default()
+    });
+    let mut clipboard = Clipboard::new().unwrap();
+
+    // 捕获源开始监听之前的变化收不到，没收到时换一段文本再选一次
+    let mut received = None;
+    for attempt in 0..5 {
+        thread::sleep(Duration::from_millis(200));
+        let text = format!("用鼠标选中的第 {} 段文本", attempt);
+        clipboard.set().clipboard(LinuxClipboardKind::Primary).text(text).unwrap();
+        if let Ok(event) = handle.events().recv_timeout(Duration::from_secs(2)) {
+            received = Some(event);
+            break;
+        }
+    }
+    handle.shutdown();
+
+    let event = received.expect("没有收到捕获");
+    assert_eq!(event.mode, CaptureMode::PrimarySelection);
+    assert!(event.text.starts_with("用鼠标选中的第 "), "{}", event.text);
+    assert!(!event.sensitive);
+}